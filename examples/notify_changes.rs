@@ -0,0 +1,103 @@
+///
+/// This example shows how you can notify users about changes to their timetable.
+/// It compares the current week's timetable to a snapshot that was saved by the
+/// previous run and prints what has changed, then saves the new snapshot.
+///
+/// Credentials are read from the `UNTIS_SERVER`, `UNTIS_SCHOOL`, `UNTIS_USER` and
+/// `UNTIS_PASSWORD` environment variables. The snapshot path can be passed as the
+/// first argument and defaults to `timetable_snapshot.json`.
+///
+use serde::{Deserialize, Serialize};
+use std::{fs, io};
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    week_start: untis::Date,
+    lessons: Vec<untis::Lesson>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), untis::Error> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("timetable_snapshot.json"));
+
+    let mut client = untis::Client::login_from_env().await?;
+
+    let week_start = untis::Date::today().relative_week_begin();
+    let week_end = untis::Date::today().relative_week_end();
+    let lessons = client.own_timetable_current_week_sorted().await?;
+
+    // Only compare against a snapshot of the same week, everything else would show up as changed.
+    let previous = match fs::read_to_string(&path) {
+        Ok(json) => Some(serde_json::from_str::<Snapshot>(&json)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    }
+    .filter(|snapshot| snapshot.week_start == week_start);
+
+    match previous {
+        None => println!(
+            "No snapshot of the week starting {} yet, saving {} lessons.",
+            week_start.short_date(),
+            lessons.len()
+        ),
+        Some(previous) => {
+            let diff = untis::diff_timetables(&previous.lessons, &lessons);
+            if diff.is_empty() {
+                println!("Nothing has changed.");
+            } else if lessons.is_empty() {
+                // All lessons disappearing at once usually means the week became a holiday.
+                let holidays = client.holidays().await?;
+                let holiday = holidays.iter().find(|holiday| {
+                    holiday.start_date <= week_end && week_start <= holiday.end_date
+                });
+                match holiday {
+                    Some(holiday) => {
+                        println!("All lessons were removed, it's {} now.", holiday.long_name)
+                    }
+                    None => println!("All {} lessons were removed.", diff.removed.len()),
+                }
+            } else {
+                print_diff(&diff);
+            }
+        }
+    }
+
+    let snapshot = Snapshot {
+        week_start,
+        lessons,
+    };
+    fs::write(&path, serde_json::to_string_pretty(&snapshot)?)?;
+
+    client.logout().await
+}
+
+fn print_diff(diff: &untis::TimetableDiff) {
+    for lesson in &diff.added {
+        println!("New: {}", describe(lesson));
+    }
+    for lesson in &diff.removed {
+        println!("Removed: {}", describe(lesson));
+    }
+    for change in &diff.changed {
+        let kinds = change
+            .kinds
+            .iter()
+            .map(|kind| format!("{:?}", kind).to_lowercase())
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("Changed ({}): {}", kinds, describe(&change.after));
+    }
+}
+
+fn describe(lesson: &untis::Lesson) -> String {
+    format!(
+        "{} {}-{} {} in {}",
+        lesson.date.short_date(),
+        lesson.start_time.format("%H:%M"),
+        lesson.end_time.format("%H:%M"),
+        lesson.primary_subject().unwrap_or("?"),
+        lesson.primary_room().unwrap_or("?"),
+    )
+}
@@ -19,7 +19,7 @@ async fn main() -> Result<(), untis::Error> {
             .timetable_current_week(&teacher.id, &untis::ElementType::Teacher)
             .await?;
 
-        timetable.sort_unstable_by_key(|lesson| (lesson.date, lesson.start_time));
+        untis::sort_lessons(&mut timetable);
 
         println!(
             "{} {}'s schedule this week:",
@@ -29,7 +29,7 @@ async fn main() -> Result<(), untis::Error> {
         for lesson in timetable {
             println!(
                 "{}, {}-{}",
-                weekday(lesson.date),
+                lesson.date.weekday_name(),
                 *lesson.start_time,
                 *lesson.end_time
             )
@@ -40,7 +40,3 @@ async fn main() -> Result<(), untis::Error> {
 
     Ok(())
 }
-
-fn weekday(date: untis::Date) -> String {
-    date.format("%A").to_string()
-}
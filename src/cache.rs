@@ -0,0 +1,131 @@
+use crate::{datetime::Date, resources::*};
+use std::collections::VecDeque;
+
+/// Master data that rarely changes during a session and is shared by the higher-level helpers of the client.
+/// Every list is filled the first time it is fetched and replaced whenever it is fetched explicitly.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MasterData {
+    pub teachers: Option<Vec<Teacher>>,
+    pub subjects: Option<Vec<Subject>>,
+    pub rooms: Option<Vec<Room>>,
+    pub classes: Option<Vec<Class>>,
+    pub timegrid: Option<Vec<TimegridDay>>,
+    pub schoolyears: Option<Vec<Schoolyear>>,
+    /// Only the ids of students are kept, to avoid holding on to their personal data.
+    pub student_ids: Option<Vec<usize>>,
+    /// The logged in user's own name, `Some(None)` if it was looked up but not found.
+    pub own_name: Option<Option<String>>,
+}
+
+/// Identifies a cached timetable response.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct TimetableKey {
+    pub element: ElementRef,
+    pub start_date: Date,
+    pub end_date: Date,
+    pub import_time: i64,
+}
+
+/// Timetable responses, valid as long as the school's last import time doesn't change.
+///
+/// Entries are evicted when a newer import time is seen, because they can never be hit again, and in
+/// least-recently-used order once the cache is full. A capacity of 0 disables the cache.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TimetableCache {
+    capacity: usize,
+    entries: VecDeque<(TimetableKey, Vec<Lesson>)>,
+}
+
+impl TimetableCache {
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.entries.truncate(capacity);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn get(&mut self, key: &TimetableKey) -> Option<Vec<Lesson>> {
+        let index = self.entries.iter().position(|(entry, _)| entry == key)?;
+        let entry = self.entries.remove(index)?;
+        let lessons = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(lessons)
+    }
+
+    pub fn insert(&mut self, key: TimetableKey, lessons: Vec<Lesson>) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.entries
+            .retain(|(entry, _)| entry.import_time >= key.import_time && *entry != key);
+        self.entries.truncate(self.capacity - 1);
+        self.entries.push_front((key, lessons));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: usize, import_time: i64) -> TimetableKey {
+        let date = Date::today();
+        TimetableKey {
+            element: ElementRef::new(id, ElementType::Class),
+            start_date: date,
+            end_date: date,
+            import_time,
+        }
+    }
+
+    #[test]
+    fn disabled_cache_stores_nothing() {
+        let mut cache = TimetableCache::default();
+        cache.insert(key(1, 0), vec![]);
+        assert_eq!(cache.get(&key(1, 0)), None);
+    }
+
+    #[test]
+    fn cache_hits_only_same_import_time() {
+        let mut cache = TimetableCache::default();
+        cache.set_capacity(4);
+        cache.insert(key(1, 0), vec![]);
+
+        assert_eq!(cache.get(&key(1, 0)), Some(vec![]));
+        assert_eq!(cache.get(&key(1, 1)), None);
+    }
+
+    #[test]
+    fn cache_evicts_stale_entries() {
+        let mut cache = TimetableCache::default();
+        cache.set_capacity(4);
+        cache.insert(key(1, 0), vec![]);
+        cache.insert(key(2, 1), vec![]);
+
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.get(&key(1, 0)), None);
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = TimetableCache::default();
+        cache.set_capacity(2);
+        cache.insert(key(1, 0), vec![]);
+        cache.insert(key(2, 0), vec![]);
+        cache.get(&key(1, 0));
+        cache.insert(key(3, 0), vec![]);
+
+        assert!(cache.get(&key(1, 0)).is_some());
+        assert!(cache.get(&key(2, 0)).is_none());
+        assert!(cache.get(&key(3, 0)).is_some());
+    }
+}
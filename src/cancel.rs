@@ -0,0 +1,45 @@
+use crate::error::Error;
+use futures_util::future::{self, Either};
+use std::future::Future;
+use tokio_util::sync::CancellationToken;
+
+/// Runs a request until it completes or `token` is cancelled, whichever happens first.
+///
+/// On cancellation the request's future is dropped, which aborts the in-flight HTTP call right away, and
+/// [`Error::Cancelled`] is returned. See the [`Client`](crate::Client) docs for what dropping a request leaves behind.
+///
+/// ```no_run
+/// # async fn example(mut client: untis::Client) -> Result<(), untis::Error> {
+/// let token = tokio_util::sync::CancellationToken::new();
+/// let students = untis::cancellable(&token, client.students()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn cancellable<T, F>(token: &CancellationToken, request: F) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    let cancelled = std::pin::pin!(token.cancelled());
+    let request = std::pin::pin!(request);
+    match future::select(request, cancelled).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(Error::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelled_requests_return_an_error() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = cancellable(&token, future::pending::<Result<(), Error>>()).await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        let token = CancellationToken::new();
+        let result = cancellable(&token, async { Ok(42) }).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+}
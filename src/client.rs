@@ -1,6 +1,19 @@
-use chrono::TimeZone;
+use chrono::{TimeZone, Weekday};
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-use crate::{datetime::Date, error::Error, jsonrpc, params, resources::*, Session};
+use crate::{
+    cache::{MasterData, TimetableCache, TimetableKey},
+    datetime::{Date, Time},
+    diff::{diff_timetables, planned_timetable, TimetableDiff},
+    error::Error,
+    group_by_student_group, jsonrpc, params,
+    resources::*,
+    sort_lessons, ClientMetrics, LessonGroup, PartialTimetable, ResolveContext, ResolvedLesson,
+    RetryPolicy, Session, SharedClient, TimetableOptions, Timetables, Workload,
+};
 
 /// Client for accessing the Untis API. Can be constructed by [`Client::login()`](Self::login) or [`School::client_login()`](School::client_login).
 ///
@@ -17,11 +30,31 @@ use crate::{datetime::Date, error::Error, jsonrpc, params, resources::*, Session
 ///     }
 /// }
 /// ```
+///
+/// # Cancellation
+/// Every request method is cancellation-safe: dropping its future, e.g. in a losing `tokio::select!` branch or via
+/// `untis::cancellable()`, aborts the in-flight HTTP call and leaves the client usable. A dropped
+/// call simply has no effect, a list it would have cached isn't cached and an interrupted
+/// [`ensure_valid()`](Self::ensure_valid) keeps the old session. Helpers that send several requests, like
+/// [`timetables_between()`](Self::timetables_between), abort all of them.
+#[must_use = "the session stays open on the server until it times out, call `logout()` when done"]
 pub struct Client {
     rpc_client: jsonrpc::Client,
     session: Session,
+    server: String,
+    credentials: Credentials,
+    master_data: MasterData,
+    timetable_cache: TimetableCache,
+    timetable_options: TimetableOptions,
+    week_start: Weekday,
 }
 
+/// How many days [`Client::own_next_lesson()`] looks ahead.
+const NEXT_LESSON_LOOKAHEAD_DAYS: u64 = 7;
+
+/// How many requests the higher-level helpers send to the server at the same time.
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+
 impl Client {
     /// Method for creating a new session.
     /// The `server` and `school` parameter both depend on the school that the user is part of; You can get `server` from
@@ -32,80 +65,614 @@ impl Client {
         username: &str,
         password: &str,
     ) -> Result<Self, Error> {
-        let params = params::AuthenticateParams {
-            client: "untis-rs",
-            user: username,
-            password,
+        let credentials = Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
         };
-        let mut rpc_client = jsonrpc::Client::new(&make_untis_url(server, school));
-        let session: Session = rpc_client.request("authenticate", params).await?;
+        let rpc_client = jsonrpc::Client::new(&make_untis_url(server, school));
+        let session = authenticate(&rpc_client, &credentials).await?;
+        Ok(Self {
+            rpc_client,
+            session,
+            server: server.to_string(),
+            credentials,
+            master_data: MasterData::default(),
+            timetable_cache: TimetableCache::default(),
+            timetable_options: TimetableOptions::default(),
+            week_start: Weekday::Mon,
+        })
+    }
+
+    /// Like [`login()`](Self::login), but tries multiple servers in the given order until one responds,
+    /// e.g. the ones from [`School::candidate_servers()`] for a school that is migrating between servers.
+    ///
+    /// Only failures to connect to a server, including timeouts, move on to the next one. Any other error, most
+    /// importantly invalid credentials, is returned right away. Use [`server()`](Self::server) to find out which
+    /// server was used.
+    pub async fn login_with_fallback(
+        servers: &[&str],
+        school: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<Self, Error> {
+        let mut last_err = Error::NotFound;
+        for server in servers {
+            match Self::login(server, school, username, password).await {
+                Err(err @ (Error::Connect(_) | Error::Timeout(_))) => last_err = err,
+                result => return result,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Logs in using the `UNTIS_SERVER`, `UNTIS_SCHOOL`, `UNTIS_USER` and `UNTIS_PASSWORD` environment variables.
+    /// Returns [`Error::MissingEnvVar`] naming the first variable that is unset or empty.
+    pub async fn login_from_env() -> Result<Self, Error> {
+        let server = env_var("UNTIS_SERVER")?;
+        let school = env_var("UNTIS_SCHOOL")?;
+        let username = env_var("UNTIS_USER")?;
+        let password = env_var("UNTIS_PASSWORD")?;
+        Self::login(&server, &school, &username, &password).await
+    }
+
+    /// Logs in again with the same credentials and returns an independent client with a new session, e.g. for a
+    /// second stream of requests that runs concurrently with this one.
+    ///
+    /// `Client` doesn't implement `Clone`, because a clone that shared the session would be logged out together with
+    /// the original and couldn't keep its own caches consistent. The returned client keeps the timetable options,
+    /// week start, retry policy and cache capacity, but starts with empty caches, no [request log](Self::record_requests) and fresh
+    /// [metrics](Self::metrics). Each client has to be logged out on its own. To share a single session between
+    /// tasks instead, use [`into_shared()`](Self::into_shared).
+    pub async fn fork(&self) -> Result<Self, Error> {
+        let rpc_client = self.rpc_client.fork();
+        let session = authenticate(&rpc_client, &self.credentials).await?;
+        let mut timetable_cache = TimetableCache::default();
+        timetable_cache.set_capacity(self.timetable_cache.capacity());
         Ok(Self {
             rpc_client,
             session,
+            server: self.server.clone(),
+            credentials: self.credentials.clone(),
+            master_data: MasterData::default(),
+            timetable_cache,
+            timetable_options: self.timetable_options,
+            week_start: self.week_start,
         })
     }
 
+    /// Calls an RPC method that this crate doesn't wrap, using the session of this client.
+    ///
+    /// The request goes through the same retry policy, size limit, metrics and recording as every other request, and
+    /// errors are reported the same way. `params` are sent as the request's parameters, use `()` for none. The result
+    /// can be any type that the response deserializes into, e.g. [`serde_json::Value`].
+    ///
+    /// ```no_run
+    /// # async fn example(mut client: untis::Client) -> Result<(), untis::Error> {
+    /// let exam_types: serde_json::Value = client.request("getExamTypes", ()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request<R: DeserializeOwned, P: Serialize>(
+        &mut self,
+        method: &'static str,
+        params: P,
+    ) -> Result<R, Error> {
+        self.rpc_client.request(method, params).await
+    }
+
+    /// Returns whether the server knows the given RPC method, e.g. to check for methods that older WebUntis
+    /// installations don't have before calling them.
+    ///
+    /// The JSON-RPC API doesn't report a version or a list of methods, so this calls `method` without parameters and
+    /// only reports `false` if the server answers with [`MethodNotFound`](jsonrpc::ErrorCode::MethodNotFound). Any
+    /// other answer, including errors about missing parameters or rights, means that the method exists. Never pass
+    /// `logout` or `authenticate`, those would be executed.
+    pub async fn supports(&mut self, method: &'static str) -> Result<bool, Error> {
+        let result: Result<serde_json::Value, Error> = self.request(method, ()).await;
+        match result {
+            Err(Error::Rpc(err)) => Ok(err.code != jsonrpc::ErrorCode::MethodNotFound.as_isize()),
+            Err(err) => Err(err),
+            Ok(_) => Ok(true),
+        }
+    }
+
+    /// Records every following request and its response to `sink`, one JSON line per exchange.
+    /// Passwords and session ids are redacted. The log can be read back using [`jsonrpc::read_log()`].
+    pub fn record_requests<W: std::io::Write + Send + 'static>(&mut self, sink: W) {
+        self.rpc_client.set_recorder(Box::new(sink));
+    }
+
+    /// Sets the function that creates the JSON-RPC id of every following request, e.g. to use UUIDs that can be
+    /// correlated with the logs of other services. By default, ids count up from 1 for every client.
+    ///
+    /// Untis doesn't interpret the ids, they only need to be unique among the requests that are in flight at once.
+    /// The generator is kept by [`fork()`](Self::fork) and [`into_shared()`](Self::into_shared).
+    pub fn set_request_id_generator<F: Fn() -> String + Send + Sync + 'static>(
+        &mut self,
+        generator: F,
+    ) {
+        self.rpc_client
+            .set_id_generator(std::sync::Arc::new(generator));
+    }
+
+    /// Sets the policy for retrying requests that were rate-limited by the server.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.rpc_client.set_retry_policy(policy);
+    }
+
+    /// Sets RPC error codes that are retried like rate-limited requests, using the [retry policy](Self::set_retry_policy),
+    /// e.g. nonstandard codes that some schools return for temporary problems. No codes are retried by default.
+    ///
+    /// Retries are counted separately from those after rate limiting. Only add codes that are known to be temporary,
+    /// retrying e.g. [`InvalidCredentials`](jsonrpc::ErrorCode::InvalidCredentials) can get the account locked.
+    pub fn set_retry_on_codes(&mut self, codes: &[isize]) {
+        self.rpc_client.set_retry_on_codes(codes);
+    }
+
+    /// Sets the largest response body in bytes that is read, larger ones fail with [`Error::ResponseTooLarge`] before
+    /// they are parsed. This protects services from running out of memory because of a broken or malicious server
+    /// or proxy.
+    ///
+    /// The default of [`DEFAULT_MAX_BODY_SIZE`](jsonrpc::DEFAULT_MAX_BODY_SIZE), 64 MiB, is far above what even large
+    /// schools send, e.g. for their list of students. Raise it if requests for very long ranges fail anyway.
+    pub fn set_max_response_size(&mut self, bytes: usize) {
+        self.rpc_client.set_max_body_size(bytes);
+    }
+
+    /// Caches up to `capacity` timetable responses, 0 disables the cache, which is the default.
+    ///
+    /// While enabled, every timetable request first checks the school's [last update time](Self::last_update_time).
+    /// If a response for the same element and dates was cached since then, it is returned instead of fetching it again.
+    pub fn set_timetable_cache_capacity(&mut self, capacity: usize) {
+        self.timetable_cache.set_capacity(capacity);
+    }
+
+    /// Clears every cache of this client, so that all following calls fetch their data again, e.g. after the school's
+    /// [last update time](Self::last_update_time) advanced. Combines [`refresh_master_data()`](Self::refresh_master_data)
+    /// and [`refresh_timetable_caches()`](Self::refresh_timetable_caches).
+    ///
+    /// Nothing is fetched right away. Status data and holidays aren't cached, so they are always up to date.
+    pub fn refresh(&mut self) {
+        self.refresh_master_data();
+        self.refresh_timetable_caches();
+    }
+
+    /// Clears the cached master data: the lists of teachers, subjects, rooms and classes, the ids of students, the
+    /// schoolyears, the timegrid and the user's [display name](Self::session_display_name).
+    pub fn refresh_master_data(&mut self) {
+        self.master_data = MasterData::default();
+    }
+
+    /// Clears the cached timetable responses, see [`set_timetable_cache_capacity()`](Self::set_timetable_cache_capacity).
+    /// The capacity itself is kept.
+    pub fn refresh_timetable_caches(&mut self) {
+        self.timetable_cache.clear();
+    }
+
+    /// Sets which optional lesson fields are requested by all subsequent timetable calls.
+    /// Cached timetables are dropped, since they may contain fields that were turned off.
+    pub fn set_default_timetable_options(&mut self, options: TimetableOptions) {
+        self.timetable_options = options;
+        self.timetable_cache.clear();
+    }
+
+    /// Sets the first day of the school's week, which is monday by default.
+    ///
+    /// All week-relative helpers like [`own_timetable_current_week()`](Self::own_timetable_current_week) fetch six days
+    /// starting on this day. Untis doesn't report the week start through the JSON-RPC API, not even in the
+    /// [status data](Self::status_data), so schools with weeks starting on sunday have to configure it here.
+    pub fn set_week_start(&mut self, first_day: Weekday) {
+        self.week_start = first_day;
+    }
+
     /// Returns the active session.
     pub fn session(&self) -> &Session {
         &self.session
     }
 
+    /// Returns the number of requests, failed requests by kind and received bytes since the client was created,
+    /// e.g. for graphing call volume and error rates.
+    pub fn metrics(&self) -> ClientMetrics {
+        self.rpc_client.metrics()
+    }
+
+    /// Returns when the server last answered a request of this client, or when the client was created if it never did.
+    /// Untis keeps sessions alive as long as they are used, so this is when the session's idle timeout started.
+    pub fn last_activity(&self) -> Instant {
+        self.rpc_client.last_activity()
+    }
+
+    /// Returns whether the session has probably expired, because no request was answered for at least `idle_timeout`.
+    ///
+    /// Untis ends sessions after a period of inactivity configured by each server, often 10 minutes, which
+    /// isn't reported through the API. It has to be supplied by the caller.
+    pub fn is_probably_expired(&self, idle_timeout: Duration) -> bool {
+        self.last_activity().elapsed() >= idle_timeout
+    }
+
+    /// Logs in again with the original credentials if the session [has probably expired](Self::is_probably_expired).
+    ///
+    /// Call this before a batch of requests after the client was idle, so that the first request of the batch doesn't
+    /// fail with an expired session.
+    pub async fn ensure_valid(&mut self, idle_timeout: Duration) -> Result<(), Error> {
+        if self.is_probably_expired(idle_timeout) {
+            self.session = authenticate(&self.rpc_client, &self.credentials).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the server that this client is logged in to.
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
     /// Returns the last time that any timetable at this school was updated.
     pub async fn last_update_time(&mut self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
-        let ts: i64 = self.rpc_client.request("getLatestImportTime", ()).await?;
-        Ok(chrono::Utc.timestamp_millis_opt(ts).unwrap())
+        fetch_last_update_time(&self.rpc_client).await
+    }
+
+    /// Returns the last time that any timetable at this school was updated, converted to the given timezone.
+    /// Usually this should be the school's timezone, e.g. [`chrono::Local`] if the application runs at the school.
+    pub async fn last_update_time_local<Tz: TimeZone>(
+        &mut self,
+        tz: &Tz,
+    ) -> Result<chrono::DateTime<Tz>, Error> {
+        Ok(self.last_update_time().await?.with_timezone(tz))
     }
 
     /// Returns status data that can be used for displaying a timetable.
     pub async fn status_data(&mut self) -> Result<StatusData, Error> {
-        self.rpc_client.request("getStatusData", ()).await
+        fetch_status_data(&self.rpc_client).await
     }
 
     /// Retrieves the current schoolyear.
     pub async fn current_schoolyear(&mut self) -> Result<Schoolyear, Error> {
-        self.rpc_client.request("getCurrentSchoolyear", ()).await
+        fetch_current_schoolyear(&self.rpc_client).await
     }
 
     /// Retrieves a list of all schoolyears.
     pub async fn schoolyears(&mut self) -> Result<Vec<Schoolyear>, Error> {
-        self.rpc_client.request("getSchoolyears", ()).await
+        let schoolyears = fetch_schoolyears(&self.rpc_client).await?;
+        self.master_data.schoolyears = Some(schoolyears.clone());
+        Ok(schoolyears)
+    }
+
+    /// Returns the cached list of schoolyears, fetching it if necessary.
+    async fn cached_schoolyears(&mut self) -> Result<Vec<Schoolyear>, Error> {
+        match &self.master_data.schoolyears {
+            Some(schoolyears) => Ok(schoolyears.clone()),
+            None => self.schoolyears().await,
+        }
+    }
+
+    /// Retrieves a list of all schoolyears, together with the index of the current one.
+    /// The index is `None` if today isn't part of any schoolyear, e.g. during summer holidays at some schools.
+    pub async fn schoolyears_with_current(
+        &mut self,
+    ) -> Result<(Vec<Schoolyear>, Option<usize>), Error> {
+        let years = self.schoolyears().await?;
+        let today = Date::today();
+        let current = years.iter().position(|year| year.is_current(&today));
+        Ok((years, current))
     }
 
     /// Retrieves the holidays in the current schoolyear.
     pub async fn holidays(&mut self) -> Result<Vec<Holiday>, Error> {
-        self.rpc_client.request("getHolidays", ()).await
+        fetch_holidays(&self.rpc_client).await
+    }
+
+    /// Retrieves the holidays that overlap with a schoolyear, e.g. one from [`schoolyears()`](Self::schoolyears).
+    ///
+    /// `getHolidays` doesn't take a schoolyear or date range, so this filters the holidays that the server returns.
+    /// Which ones those are is up to the server, many only return those of the current schoolyear. Holidays of
+    /// other years, especially of years that haven't been set up by the school yet, may therefore be missing.
+    pub async fn holidays_for_schoolyear(
+        &mut self,
+        year: &Schoolyear,
+    ) -> Result<Vec<Holiday>, Error> {
+        let mut holidays = self.holidays().await?;
+        holidays.retain(|holiday| {
+            year.clamp_range(holiday.start_date, holiday.end_date)
+                .is_some()
+        });
+        Ok(holidays)
+    }
+
+    /// Retrieves the holidays that overlap with the week that a given date is in, e.g. to shade them in a weekly view.
+    ///
+    /// Holidays that only partly overlap with the week are included with their full dates, use
+    /// [`Holiday::clamp_range()`] with the week's [bounds](Date::week_bounds) to get the affected days.
+    pub async fn week_holidays(&mut self, date: &Date) -> Result<Vec<Holiday>, Error> {
+        let (start, end) = date.week_bounds(self.week_start);
+        let mut holidays = self.holidays().await?;
+        holidays.retain(|holiday| holiday.clamp_range(start, end).is_some());
+        Ok(holidays)
+    }
+
+    /// Returns the school's timegrid, i.e. the periods of every weekday.
+    /// See [`Lesson::period_index()`] for matching lessons to them.
+    ///
+    /// The timegrid rarely changes, so it is fetched once and then cached together with the other master data for
+    /// as long as this client exists. Use [`refresh_timegrid()`](Self::refresh_timegrid) to fetch it again.
+    pub async fn timegrid(&mut self) -> Result<Vec<TimegridDay>, Error> {
+        match &self.master_data.timegrid {
+            Some(timegrid) => Ok(timegrid.clone()),
+            None => self.refresh_timegrid().await,
+        }
+    }
+
+    /// Fetches the school's timegrid and replaces the cached one, see [`timegrid()`](Self::timegrid).
+    pub async fn refresh_timegrid(&mut self) -> Result<Vec<TimegridDay>, Error> {
+        let timegrid = fetch_timegrid(&self.rpc_client).await?;
+        self.master_data.timegrid = Some(timegrid.clone());
+        Ok(timegrid)
     }
 
     /// Retrieves the list of rooms in the user's school.
     pub async fn rooms(&mut self) -> Result<Vec<Room>, Error> {
-        self.rpc_client.request("getRooms", ()).await
+        let rooms = fetch_rooms(&self.rpc_client).await?;
+        self.master_data.rooms = Some(rooms.clone());
+        Ok(rooms)
+    }
+
+    /// Returns the cached list of rooms, fetching it if necessary.
+    async fn cached_rooms(&mut self) -> Result<Vec<Room>, Error> {
+        match &self.master_data.rooms {
+            Some(rooms) => Ok(rooms.clone()),
+            None => self.rooms().await,
+        }
+    }
+
+    /// Returns the rooms that are still in use, i.e. whose [`active`](Room::active) flag is set, e.g. for pickers
+    /// that shouldn't offer decommissioned rooms. The list is cached, see [`refresh()`](Self::refresh).
+    pub async fn active_rooms(&mut self) -> Result<Vec<Room>, Error> {
+        let mut rooms = self.cached_rooms().await?;
+        rooms.retain(|room| room.active);
+        Ok(rooms)
     }
 
     /// Retrieves the list of classes in the user's school.
     pub async fn classes(&mut self) -> Result<Vec<Class>, Error> {
-        self.rpc_client.request("getKlassen", ()).await
+        let classes = fetch_classes(&self.rpc_client).await?;
+        self.master_data.classes = Some(classes.clone());
+        Ok(classes)
+    }
+
+    /// Returns the cached list of classes, fetching it if necessary.
+    async fn cached_classes(&mut self) -> Result<Vec<Class>, Error> {
+        match &self.master_data.classes {
+            Some(classes) => Ok(classes.clone()),
+            None => self.classes().await,
+        }
+    }
+
+    /// Like [`active_rooms()`](Self::active_rooms), but for classes.
+    pub async fn active_classes(&mut self) -> Result<Vec<Class>, Error> {
+        let mut classes = self.cached_classes().await?;
+        classes.retain(|class| class.active);
+        Ok(classes)
+    }
+
+    /// Retrieves the list of classes that have the given teacher as their primary or secondary teacher.
+    pub async fn classes_with_teacher(&mut self, teacher_id: &usize) -> Result<Vec<Class>, Error> {
+        let teacher_id = *teacher_id as isize;
+        let mut classes = self.classes().await?;
+        classes.retain(|class| class.teacher1 == teacher_id || class.teacher2 == teacher_id);
+        Ok(classes)
     }
 
     /// Retrieves the list of subjects in the user's school.
     pub async fn subjects(&mut self) -> Result<Vec<Subject>, Error> {
-        self.rpc_client.request("getSubjects", ()).await
+        let subjects = fetch_subjects(&self.rpc_client).await?;
+        self.master_data.subjects = Some(subjects.clone());
+        Ok(subjects)
+    }
+
+    /// Returns the cached list of subjects, fetching it if necessary.
+    async fn cached_subjects(&mut self) -> Result<Vec<Subject>, Error> {
+        match &self.master_data.subjects {
+            Some(subjects) => Ok(subjects.clone()),
+            None => self.subjects().await,
+        }
+    }
+
+    /// Like [`active_rooms()`](Self::active_rooms), but for subjects.
+    pub async fn active_subjects(&mut self) -> Result<Vec<Subject>, Error> {
+        let mut subjects = self.cached_subjects().await?;
+        subjects.retain(|subject| subject.active);
+        Ok(subjects)
     }
 
     /// Retrieves the list of teachers in the user's school.
     pub async fn teachers(&mut self) -> Result<Vec<Teacher>, Error> {
-        self.rpc_client.request("getTeachers", ()).await
+        let teachers = fetch_teachers(&self.rpc_client).await?;
+        self.master_data.teachers = Some(teachers.clone());
+        Ok(teachers)
+    }
+
+    /// Returns the cached list of teachers, fetching it if necessary.
+    async fn cached_teachers(&mut self) -> Result<Vec<Teacher>, Error> {
+        match &self.master_data.teachers {
+            Some(teachers) => Ok(teachers.clone()),
+            None => self.teachers().await,
+        }
+    }
+
+    /// Like [`active_rooms()`](Self::active_rooms), but for teachers.
+    pub async fn active_teachers(&mut self) -> Result<Vec<Teacher>, Error> {
+        let mut teachers = self.cached_teachers().await?;
+        teachers.retain(|teacher| teacher.active);
+        Ok(teachers)
+    }
+
+    /// Looks up a room by its short [`name`](Room::name), e.g. one referenced by a lesson.
+    /// The list of rooms is cached after it was fetched once.
+    pub async fn room_by_short_name(&mut self, name: &str) -> Result<Option<Room>, Error> {
+        let rooms = self.cached_rooms().await?;
+        Ok(rooms.into_iter().find(|room| room.name == name))
+    }
+
+    /// Looks up a class by its short [`name`](Class::name), see [`room_by_short_name()`](Self::room_by_short_name).
+    pub async fn class_by_short_name(&mut self, name: &str) -> Result<Option<Class>, Error> {
+        let classes = self.cached_classes().await?;
+        Ok(classes.into_iter().find(|class| class.name == name))
+    }
+
+    /// Looks up a subject by its short [`name`](Subject::name), see [`room_by_short_name()`](Self::room_by_short_name).
+    pub async fn subject_by_short_name(&mut self, name: &str) -> Result<Option<Subject>, Error> {
+        let subjects = self.cached_subjects().await?;
+        Ok(subjects.into_iter().find(|subject| subject.name == name))
+    }
+
+    /// Looks up a teacher by their short [`name`](Teacher::name), see [`room_by_short_name()`](Self::room_by_short_name).
+    pub async fn teacher_by_short_name(&mut self, name: &str) -> Result<Option<Teacher>, Error> {
+        let teachers = self.cached_teachers().await?;
+        Ok(teachers.into_iter().find(|teacher| teacher.name == name))
+    }
+
+    /// Retrieves the active teachers that don't teach a lesson overlapping the time range from `start` to `end` on `date`.
+    /// Cancelled lessons and lessons on which a teacher has been substituted don't count as busy.
+    /// This fetches every teacher's timetable for that day, so it requires access to teacher timetables.
+    pub async fn teachers_free_at(
+        &mut self,
+        date: &Date,
+        start: &Time,
+        end: &Time,
+    ) -> Result<Vec<Teacher>, Error> {
+        let mut teachers = self.cached_teachers().await?;
+        teachers.retain(|teacher| teacher.active);
+
+        let elements = teachers
+            .iter()
+            .map(|teacher| ElementRef::new(teacher.id, ElementType::Teacher))
+            .collect::<Vec<_>>();
+        let timetables = fetch_timetables(
+            &self.rpc_client,
+            &self.timetable_options,
+            self.week_start,
+            &elements,
+            date,
+            date,
+        )
+        .await?;
+
+        let free = teachers
+            .into_iter()
+            .zip(timetables.entries)
+            .filter(|(teacher, (_, lessons))| {
+                let id = teacher.id as isize;
+                !lessons.iter().any(|lesson| {
+                    lesson.code != LessonCode::Cancelled
+                        && lesson.overlaps(date, start, end)
+                        && (lesson.teachers.is_empty()
+                            || lesson.teachers.iter().any(|element| element.id == id))
+                })
+            })
+            .map(|(teacher, _)| teacher)
+            .collect();
+        Ok(free)
     }
 
     /// Retrieves the list of students in the user's school.
     pub async fn students(&mut self) -> Result<Vec<Student>, Error> {
-        self.rpc_client.request("getStudents", ()).await
+        let students = fetch_students(&self.rpc_client).await?;
+        self.master_data.student_ids = Some(students.iter().map(|student| student.id).collect());
+        Ok(students)
     }
 
-    /// Retrieves the user's own timetable between now and a given date.
+    /// Retrieves the names of the students in the user's school by their id, formatted as `first_name last_name`.
+    ///
+    /// `getStudents` has no way to select fields, so the server still sends the full records including gender and
+    /// external keys. Only the id, first and last name are parsed, everything else is dropped and never stored.
+    /// Note that a [request log](Self::record_requests) still contains the raw response.
+    pub async fn student_names(&mut self) -> Result<HashMap<usize, String>, Error> {
+        let students: Vec<StudentName> = self.rpc_client.request("getStudents", ()).await?;
+        self.master_data.student_ids = Some(students.iter().map(|student| student.id).collect());
+        Ok(students
+            .into_iter()
+            .map(|student| {
+                (
+                    student.id,
+                    format!("{} {}", student.fore_name, student.long_name)
+                        .trim()
+                        .to_string(),
+                )
+            })
+            .collect())
+    }
+
+    /// Returns the logged in user's name, formatted as `first_name last_name`, e.g. for a "logged in as" label.
+    ///
+    /// The authentication response only contains the user's id and type, so the name is looked up in the cached list
+    /// of teachers, or for students in [`student_names()`](Self::student_names). For students, the first call
+    /// therefore costs a full `getStudents` request for the whole school. Only the user's own name is kept afterwards,
+    /// until the cache is [cleared](Self::refresh_master_data).
+    ///
+    /// Returns `None` if the user isn't allowed to access that list, which is common for students, or if they aren't
+    /// part of it.
+    pub async fn session_display_name(&mut self) -> Result<Option<String>, Error> {
+        if let Some(name) = &self.master_data.own_name {
+            return Ok(name.clone());
+        }
+
+        let id = self.session.person_id;
+        let name = match self.session.person_type {
+            ElementType::Teacher => allow_no_access(self.cached_teachers().await)?
+                .into_iter()
+                .find(|teacher| teacher.id == id)
+                .map(|teacher| format!("{} {}", teacher.first_name, teacher.last_name)),
+            ElementType::Student => match self.student_names().await {
+                Ok(mut names) => names.remove(&id),
+                Err(err) if is_no_access(&err) => None,
+                Err(err) => return Err(err),
+            },
+            _ => None,
+        };
+        let name = name.map(|name| name.trim().to_string());
+        self.master_data.own_name = Some(name.clone());
+        Ok(name)
+    }
+
+    /// Returns the element types whose elements the user is allowed to list, e.g. to only offer those views in a UI.
+    ///
+    /// The session doesn't carry any permission data, so this requests the list of every element type and leaves out
+    /// those the server denies access to. The user's own type is always included. The lists are taken from and stored
+    /// in the master data cache, so the cost is only paid once. Of the students, only the ids are kept.
+    pub async fn queryable_element_types(&mut self) -> Result<Vec<ElementType>, Error> {
+        let own_type = self.session.person_type.clone();
+        let mut types = Vec::new();
+        if own_type == ElementType::Class || is_listable(self.cached_classes().await)? {
+            types.push(ElementType::Class);
+        }
+        if own_type == ElementType::Teacher || is_listable(self.cached_teachers().await)? {
+            types.push(ElementType::Teacher);
+        }
+        if own_type == ElementType::Subject || is_listable(self.cached_subjects().await)? {
+            types.push(ElementType::Subject);
+        }
+        if own_type == ElementType::Room || is_listable(self.cached_rooms().await)? {
+            types.push(ElementType::Room);
+        }
+        if own_type == ElementType::Student || is_listable(self.cached_student_ids().await)? {
+            types.push(ElementType::Student);
+        }
+        Ok(types)
+    }
+
+    /// Retrieves the user's own timetable between today and a given date.
+    /// Both dates are inclusive, so lessons on `end_date` are part of the result.
+    ///
+    /// This only looks forward, see [`own_timetable_since()`](Self::own_timetable_since) for lessons that already
+    /// took place and [`own_timetable_between()`](Self::own_timetable_between) for arbitrary ranges.
     pub async fn own_timetable_until(&mut self, end_date: &Date) -> Result<Vec<Lesson>, Error> {
         self.own_timetable_between(&Date::today(), end_date).await
     }
 
+    /// Retrieves the user's own timetable between a given date in the past and today, both inclusive.
+    pub async fn own_timetable_since(&mut self, start_date: &Date) -> Result<Vec<Lesson>, Error> {
+        self.own_timetable_between(start_date, &Date::today()).await
+    }
+
     /// Retrieves the users's own timetable for the current week.
     pub async fn own_timetable_current_week(&mut self) -> Result<Vec<Lesson>, Error> {
         self.own_timetable_for_week(&Date::today()).await
@@ -113,26 +680,184 @@ impl Client {
 
     /// Retrieves the users's own timetable for the week that a given date is in.
     pub async fn own_timetable_for_week(&mut self, date: &Date) -> Result<Vec<Lesson>, Error> {
-        self.own_timetable_between(&date.relative_week_begin(), &date.relative_week_end())
+        self.own_timetable_between(
+            &date.relative_week_begin_on(self.week_start),
+            &date.relative_week_end_on(self.week_start),
+        )
+        .await
+    }
+
+    /// Retrieves the timetable of the logged in student's whole class for the week that a given date is in, using the
+    /// [`class_id`](Session::class_id) of the session. Fails with [`Error::NotFound`] if the user isn't in a class.
+    pub async fn own_class_timetable_for_week(
+        &mut self,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        let class_id = self.session.class_id.ok_or(Error::NotFound)?;
+        self.timetable_for_week(&class_id, &ElementType::Class, date)
             .await
     }
 
-    /// Retrieves the users's own timetable between two dates.
+    /// Retrieves the lessons that the logged in teacher covers for other teachers in the week that a given date is in.
+    ///
+    /// Untis lists a substitute teacher with the id of the teacher they replace as [`orgid`](IdItem::orgid), so these
+    /// are the lessons of the own timetable where the user has an `orgid` other than their own id, see
+    /// [`Lesson::is_substituted_by()`]. Cancelled lessons are included, so that duties that were called off can be
+    /// shown as such. Fails with [`Error::NotFound`] if the user isn't a teacher.
+    pub async fn own_substitution_duties_for_week(
+        &mut self,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        if !matches!(self.session.person_type, ElementType::Teacher) {
+            return Err(Error::NotFound);
+        }
+        let teacher_id = self.session.person_id;
+        let mut lessons = self.own_timetable_for_week(date).await?;
+        lessons.retain(|lesson| lesson.is_substituted_by(teacher_id));
+        Ok(lessons)
+    }
+
+    /// Retrieves the users's own timetable between two dates, both inclusive.
+    ///
+    /// Ranges that span multiple schoolyears, e.g. from late August into September, are split at the start and end of
+    /// every schoolyear and fetched piece by piece, as servers answer such requests inconsistently. The list of
+    /// schoolyears is cached after it was fetched once.
+    ///
+    /// Fails with [`Error::NotFound`] if the account isn't linked to a teacher or student, see
+    /// [`Session::own_element()`].
     pub async fn own_timetable_between(
         &mut self,
         start_date: &Date,
         end_date: &Date,
     ) -> Result<Vec<Lesson>, Error> {
-        self.timetable_between(
-            &self.session.person_id.clone(),
-            &self.session.person_type.clone(),
-            start_date,
-            end_date,
-        )
-        .await
+        let element = self.session.own_element().ok_or(Error::NotFound)?;
+        let schoolyears = allow_no_access(self.cached_schoolyears().await)?;
+        let mut lessons = Vec::new();
+        for (start, end) in split_at_schoolyears(start_date, end_date, &schoolyears) {
+            lessons.extend(
+                self.element_timetable_between(&element, &start, &end)
+                    .await?,
+            );
+        }
+        Ok(lessons)
+    }
+
+    /// Returns the user's next lesson that hasn't started yet and isn't cancelled, e.g. for a "next class" widget.
+    ///
+    /// The current time is taken from the system's local timezone, which should be the school's. Only the next
+    /// seven days are searched, so this returns `None` during longer holidays.
+    pub async fn own_next_lesson(&mut self) -> Result<Option<Lesson>, Error> {
+        self.own_next_lesson_after(chrono::Local::now().naive_local())
+            .await
+    }
+
+    /// Like [`own_next_lesson()`](Self::own_next_lesson), but for a given time in the school's timezone.
+    pub async fn own_next_lesson_after(
+        &mut self,
+        now: chrono::NaiveDateTime,
+    ) -> Result<Option<Lesson>, Error> {
+        let start_date = Date(now.date());
+        let end_date = now
+            .date()
+            .checked_add_days(chrono::Days::new(NEXT_LESSON_LOOKAHEAD_DAYS))
+            .map_or(start_date, Date);
+        let lessons = self.own_timetable_between(&start_date, &end_date).await?;
+        Ok(next_lesson(lessons, now))
     }
 
-    /// Retrieves an element's timetable between now and a given date.
+    /// Like [`own_timetable_until()`](Self::own_timetable_until), but sorted using [`sort_lessons()`].
+    pub async fn own_timetable_until_sorted(
+        &mut self,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        sorted(self.own_timetable_until(end_date).await)
+    }
+
+    /// Like [`own_timetable_current_week()`](Self::own_timetable_current_week), but sorted using [`sort_lessons()`].
+    pub async fn own_timetable_current_week_sorted(&mut self) -> Result<Vec<Lesson>, Error> {
+        sorted(self.own_timetable_current_week().await)
+    }
+
+    /// Like [`own_timetable_for_week()`](Self::own_timetable_for_week), but sorted using [`sort_lessons()`].
+    pub async fn own_timetable_for_week_sorted(
+        &mut self,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        sorted(self.own_timetable_for_week(date).await)
+    }
+
+    /// Like [`own_timetable_between()`](Self::own_timetable_between), but sorted using [`sort_lessons()`].
+    pub async fn own_timetable_between_sorted(
+        &mut self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        sorted(self.own_timetable_between(start_date, end_date).await)
+    }
+
+    /// Retrieves the users's own timetable for the week that a given date is in, sorted chronologically and grouped by
+    /// student group. See [`group_by_student_group()`] for how lessons without a group are handled.
+    pub async fn own_timetable_for_week_grouped(
+        &mut self,
+        date: &Date,
+    ) -> Result<Vec<LessonGroup>, Error> {
+        let lessons = self.own_timetable_for_week_sorted(date).await?;
+        Ok(group_by_student_group(&lessons))
+    }
+
+    /// Retrieves the user's own timetable for the week that a given date is in and compares it to a previous snapshot
+    /// of the same week, e.g. one persisted the last time this was called. See [`diff_timetables()`] for details.
+    pub async fn own_timetable_diff(
+        &mut self,
+        previous: &[Lesson],
+        date: &Date,
+    ) -> Result<TimetableDiff, Error> {
+        let current = self.own_timetable_for_week(date).await?;
+        Ok(diff_timetables(previous, &current))
+    }
+
+    /// Returns what is different about the user's own week compared to the regular plan, e.g. substitutions,
+    /// cancellations and extra lessons. See [`planned_timetable()`](crate::planned_timetable) for how the plan is
+    /// reconstructed from the week's timetable.
+    pub async fn own_week_changes(&mut self, date: &Date) -> Result<TimetableDiff, Error> {
+        let lessons = self.own_timetable_for_week(date).await?;
+        Ok(diff_timetables(&planned_timetable(&lessons), &lessons))
+    }
+
+    /// Sums up what a teacher teaches between two dates, both inclusive. See [`Workload::from_lessons()`] for which
+    /// lessons are counted.
+    pub async fn teacher_workload(
+        &mut self,
+        teacher_id: &usize,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Workload, Error> {
+        let lessons = self
+            .timetable_between(teacher_id, &ElementType::Teacher, start_date, end_date)
+            .await?;
+        Ok(Workload::from_lessons(&lessons))
+    }
+
+    /// Retrieves every lesson of a subject across the school in the week that a given date is in,
+    /// e.g. to find out where and when physics is taught this week.
+    ///
+    /// Many schools don't allow subject timetables, in which case this returns [`Error::NoRight`].
+    pub async fn subject_timetable_for_week(
+        &mut self,
+        subject: &Subject,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        match self
+            .timetable_for_week(&subject.id, &ElementType::Subject, date)
+            .await
+        {
+            Err(err) if is_no_access(&err) => Err(Error::NoRight),
+            result => result,
+        }
+    }
+
+    /// Retrieves an element's timetable between today and a given date.
+    /// Both dates are inclusive, so lessons on `end_date` are part of the result.
     pub async fn timetable_until(
         &mut self,
         id: &usize,
@@ -162,13 +887,13 @@ impl Client {
         self.timetable_between(
             id,
             ty,
-            &date.relative_week_begin(),
-            &date.relative_week_end(),
+            &date.relative_week_begin_on(self.week_start),
+            &date.relative_week_end_on(self.week_start),
         )
         .await
     }
 
-    /// Retrieves an element's own timetable between two dates.
+    /// Retrieves an element's own timetable between two dates, both inclusive.
     pub async fn timetable_between(
         &mut self,
         id: &usize,
@@ -176,42 +901,868 @@ impl Client {
         start_date: &Date,
         end_date: &Date,
     ) -> Result<Vec<Lesson>, Error> {
-        let params = params::TimetableParams {
-            options: &params::TimetableParamsOpts {
-                element: &params::TimetableParamsElem { id, ty },
+        self.element_timetable_between(&ElementRef::new(*id, ty.clone()), start_date, end_date)
+            .await
+    }
+
+    /// Like [`element_timetable_between()`](Self::element_timetable_between), but parses every lesson on its own.
+    /// Lessons with an unexpected shape end up in [`PartialTimetable::failures`] instead of failing the whole request.
+    /// The timetable cache isn't used.
+    pub async fn timetable_between_tolerant(
+        &mut self,
+        element: &ElementRef,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<PartialTimetable, Error> {
+        let values = fetch_timetable(
+            &self.rpc_client,
+            &self.timetable_options,
+            self.week_start,
+            element,
+            start_date,
+            end_date,
+        )
+        .await?;
+        Ok(PartialTimetable::from_values(values))
+    }
+
+    /// Searches the user's own timetable for the week that a given date is in, e.g. for `"english smith"`.
+    ///
+    /// A lesson matches if every word of the query is part of the short or full name of one of its subjects,
+    /// teachers, rooms or classes, see [`ResolvedLesson::matches_text()`]. Full names are looked up using
+    /// [`resolve_context()`](Self::resolve_context). The matches are sorted by time.
+    pub async fn search_timetable_by_text(
+        &mut self,
+        query: &str,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        let lessons = self.own_timetable_for_week_sorted(date).await?;
+        let context = self.resolve_context().await?;
+        Ok(lessons
+            .into_iter()
+            .filter(|lesson| context.resolve(lesson).matches_text(query))
+            .collect())
+    }
+
+    /// Retrieves a timetable with the full names of all subjects, teachers, rooms and classes, sorted chronologically.
+    /// See [`resolve_context()`](Self::resolve_context) for how names are looked up.
+    pub async fn timetable_with_resolved_names(
+        &mut self,
+        element: &ElementRef,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<ResolvedLesson>, Error> {
+        let context = self.resolve_context().await?;
+        let mut lessons = self
+            .element_timetable_between(element, start_date, end_date)
+            .await?;
+        sort_lessons(&mut lessons);
+        Ok(lessons
+            .iter()
+            .map(|lesson| context.resolve(lesson))
+            .collect())
+    }
+
+    /// Returns the full names of the school's subjects, teachers, rooms and classes for resolving lessons.
+    ///
+    /// The lists are fetched once and then cached for the rest of the session. Lists that the user isn't allowed to
+    /// access are left empty, so their elements keep the short names from the lessons.
+    pub async fn resolve_context(&mut self) -> Result<ResolveContext, Error> {
+        let subjects = allow_no_access(self.cached_subjects().await)?;
+        let teachers = allow_no_access(self.cached_teachers().await)?;
+        let rooms = allow_no_access(self.cached_rooms().await)?;
+        let classes = allow_no_access(self.cached_classes().await)?;
+        Ok(ResolveContext::new(&subjects, &teachers, &rooms, &classes))
+    }
+
+    /// Returns the type of the element with the given id, e.g. to check an id from another system before
+    /// querying its timetable.
+    ///
+    /// Ids are only unique per type, so this returns `None` both if no room, class, teacher, subject or student has
+    /// the id and if more than one of them does. Lists that the user isn't allowed to access are skipped. All lists
+    /// are cached after they were fetched once.
+    pub async fn guess_element_type(&mut self, id: usize) -> Result<Option<ElementType>, Error> {
+        let mut types = Vec::new();
+        if allow_no_access(self.cached_rooms().await)?
+            .iter()
+            .any(|room| room.id == id)
+        {
+            types.push(ElementType::Room);
+        }
+        if allow_no_access(self.cached_classes().await)?
+            .iter()
+            .any(|class| class.id == id)
+        {
+            types.push(ElementType::Class);
+        }
+        if allow_no_access(self.cached_teachers().await)?
+            .iter()
+            .any(|teacher| teacher.id == id)
+        {
+            types.push(ElementType::Teacher);
+        }
+        if allow_no_access(self.cached_subjects().await)?
+            .iter()
+            .any(|subject| subject.id == id)
+        {
+            types.push(ElementType::Subject);
+        }
+        if allow_no_access(self.cached_student_ids().await)?.contains(&id) {
+            types.push(ElementType::Student);
+        }
+
+        Ok(match types.as_slice() {
+            [ty] => Some(ty.clone()),
+            _ => None,
+        })
+    }
+
+    /// Returns the cached ids of all students, fetching them if necessary.
+    async fn cached_student_ids(&mut self) -> Result<Vec<usize>, Error> {
+        match &self.master_data.student_ids {
+            Some(ids) => Ok(ids.clone()),
+            None => Ok(self.student_names().await?.into_keys().collect()),
+        }
+    }
+
+    /// Retrieves the timetable of an element referenced by id or name between two dates, both inclusive.
+    pub async fn element_timetable_between(
+        &mut self,
+        element: &ElementRef,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        if !self.timetable_cache.is_enabled() {
+            return fetch_timetable(
+                &self.rpc_client,
+                &self.timetable_options,
+                self.week_start,
+                element,
                 start_date,
                 end_date,
-                show_booking: &true,
-                show_info: &true,
-                show_subst_text: &true,
-                show_ls_text: &true,
-                show_ls_number: &true,
-                show_student_group: &true,
-                class_fields: &["id", "name"],
-                room_fields: &["id", "name"],
-                subject_fields: &["id", "name"],
-                teacher_fields: &["id", "name"],
-            },
+            )
+            .await;
+        }
+
+        let key = TimetableKey {
+            element: element.clone(),
+            start_date: *start_date,
+            end_date: *end_date,
+            import_time: self.import_timestamp().await?,
+        };
+        if let Some(lessons) = self.timetable_cache.get(&key) {
+            return Ok(lessons);
+        }
+
+        let lessons = fetch_timetable(
+            &self.rpc_client,
+            &self.timetable_options,
+            self.week_start,
+            element,
+            start_date,
+            end_date,
+        )
+        .await?;
+        self.timetable_cache.insert(key, lessons.clone());
+        Ok(lessons)
+    }
+
+    /// Watches an element's timetable for the week that a given date is in.
+    ///
+    /// The stream yields the timetable right away and then checks the school's [last update time](Self::last_update_time)
+    /// every `interval`, only fetching and yielding the timetable again when it has changed.
+    /// Errors are yielded as they occur, after which polling continues; stop consuming the stream to stop watching.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn watch(mut client: untis::Client) {
+    /// use futures_util::StreamExt;
+    ///
+    /// let date = untis::Date::today();
+    /// let interval = std::time::Duration::from_secs(300);
+    /// let updates = client.watch_timetable(&42, &untis::ElementType::Room, &date, interval);
+    /// let mut updates = std::pin::pin!(updates);
+    /// while let Some(timetable) = updates.next().await {
+    ///     println!("{:?}", timetable);
+    /// }
+    /// # }
+    /// ```
+    pub fn watch_timetable<'a>(
+        &'a mut self,
+        id: &'a usize,
+        ty: &'a ElementType,
+        date: &'a Date,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Vec<Lesson>, Error>> + 'a {
+        let state = WatchState {
+            client: self,
+            last_import_time: None,
+            started: false,
         };
-        self.rpc_client.request("getTimetable", params).await
+
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                if state.started {
+                    tokio::time::sleep(interval).await;
+                }
+                state.started = true;
+
+                let import_time = match state.client.import_timestamp().await {
+                    Ok(import_time) => import_time,
+                    Err(err) => return Some((Err(err), state)),
+                };
+                if state.last_import_time == Some(import_time) {
+                    continue;
+                }
+
+                let result = state.client.timetable_for_week(id, ty, date).await;
+                if result.is_ok() {
+                    state.last_import_time = Some(import_time);
+                }
+                return Some((result, state));
+            }
+        })
+    }
+
+    /// Returns the raw timestamp of the last update, in milliseconds.
+    async fn import_timestamp(&self) -> Result<i64, Error> {
+        fetch_import_time(&self.rpc_client).await
+    }
+
+    /// Retrieves the pairs of lessons that are booked into the same room at overlapping times, in the week that a given date is in.
+    ///
+    /// Cancelled lessons are ignored. Lessons with the same [lesson number](Lesson::lsnumber), e.g. groups of a
+    /// class that are taught in parallel, share the room on purpose and are not reported either.
+    pub async fn room_conflicts_for_week(
+        &mut self,
+        room_id: &usize,
+        date: &Date,
+    ) -> Result<Vec<(Lesson, Lesson)>, Error> {
+        let lessons = self
+            .timetable_for_week(room_id, &ElementType::Room, date)
+            .await?;
+        Ok(find_conflicts(&lessons))
+    }
+
+    /// Retrieves the timetables of multiple elements between two dates, both inclusive.
+    /// Up to four timetables are fetched at the same time.
+    pub async fn timetables_between(
+        &mut self,
+        elements: &[ElementRef],
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Timetables, Error> {
+        fetch_timetables(
+            &self.rpc_client,
+            &self.timetable_options,
+            self.week_start,
+            elements,
+            start_date,
+            end_date,
+        )
+        .await
     }
 
     /// Retrieves the list of departments in the user's school.
     pub async fn departments(&mut self) -> Result<Vec<Department>, Error> {
-        self.rpc_client.request("getDepartments", ()).await
+        fetch_departments(&self.rpc_client).await
+    }
+
+    /// Turns this client into a [`SharedClient`] that can be cloned and used from many tasks at once.
+    /// The session is kept, but cached data is dropped.
+    pub fn into_shared(self) -> SharedClient {
+        SharedClient::new(
+            self.rpc_client,
+            self.session,
+            self.server,
+            self.credentials,
+            self.timetable_options,
+            self.week_start,
+        )
     }
 
-    pub async fn logout(mut self) -> Result<(), Error> {
+    pub async fn logout(self) -> Result<(), Error> {
         self.rpc_client.request("logout", ()).await
     }
 }
 
+/// The credentials a client logged in with, kept to log in again once the session expires.
+#[derive(Clone)]
+pub(crate) struct Credentials {
+    username: String,
+    password: String,
+}
+
+pub(crate) async fn authenticate(
+    rpc_client: &jsonrpc::Client,
+    credentials: &Credentials,
+) -> Result<Session, Error> {
+    let params = params::AuthenticateParams {
+        client: "untis-rs",
+        user: &credentials.username,
+        password: &credentials.password,
+    };
+    rpc_client.request("authenticate", params).await
+}
+
+// The queries that both `Client` and `SharedClient` offer, so that the two send the same requests.
+
+pub(crate) async fn fetch_import_time(rpc_client: &jsonrpc::Client) -> Result<i64, Error> {
+    rpc_client.request("getLatestImportTime", ()).await
+}
+
+pub(crate) async fn fetch_last_update_time(
+    rpc_client: &jsonrpc::Client,
+) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+    timestamp_from_untis(fetch_import_time(rpc_client).await?)
+}
+
+pub(crate) async fn fetch_status_data(rpc_client: &jsonrpc::Client) -> Result<StatusData, Error> {
+    rpc_client.request("getStatusData", ()).await
+}
+
+pub(crate) async fn fetch_current_schoolyear(
+    rpc_client: &jsonrpc::Client,
+) -> Result<Schoolyear, Error> {
+    rpc_client.request("getCurrentSchoolyear", ()).await
+}
+
+pub(crate) async fn fetch_schoolyears(
+    rpc_client: &jsonrpc::Client,
+) -> Result<Vec<Schoolyear>, Error> {
+    rpc_client.request("getSchoolyears", ()).await
+}
+
+pub(crate) async fn fetch_holidays(rpc_client: &jsonrpc::Client) -> Result<Vec<Holiday>, Error> {
+    rpc_client.request("getHolidays", ()).await
+}
+
+pub(crate) async fn fetch_timegrid(
+    rpc_client: &jsonrpc::Client,
+) -> Result<Vec<TimegridDay>, Error> {
+    rpc_client.request("getTimegridUnits", ()).await
+}
+
+pub(crate) async fn fetch_rooms(rpc_client: &jsonrpc::Client) -> Result<Vec<Room>, Error> {
+    rpc_client.request("getRooms", ()).await
+}
+
+pub(crate) async fn fetch_classes(rpc_client: &jsonrpc::Client) -> Result<Vec<Class>, Error> {
+    rpc_client.request("getKlassen", ()).await
+}
+
+pub(crate) async fn fetch_subjects(rpc_client: &jsonrpc::Client) -> Result<Vec<Subject>, Error> {
+    rpc_client.request("getSubjects", ()).await
+}
+
+pub(crate) async fn fetch_teachers(rpc_client: &jsonrpc::Client) -> Result<Vec<Teacher>, Error> {
+    rpc_client.request("getTeachers", ()).await
+}
+
+pub(crate) async fn fetch_students(rpc_client: &jsonrpc::Client) -> Result<Vec<Student>, Error> {
+    rpc_client.request("getStudents", ()).await
+}
+
+pub(crate) async fn fetch_departments(
+    rpc_client: &jsonrpc::Client,
+) -> Result<Vec<Department>, Error> {
+    rpc_client.request("getDepartments", ()).await
+}
+
+struct WatchState<'a> {
+    client: &'a mut Client,
+    last_import_time: Option<i64>,
+    started: bool,
+}
+
 impl School {
+    /// Logs in to this school, falling back to its alternative servers if the main one can't be reached.
+    /// See [`Client::login_with_fallback()`].
+    ///
+    /// If the server doesn't know the school, this record is probably stale, e.g. because the school was migrated to
+    /// another server, and [`Error::SchoolMoved`] is returned. Search for the school again in that case, e.g. using
+    /// [`schools::get_by_id()`](crate::schools::get_by_id).
     pub async fn client_login(&self, username: &str, password: &str) -> Result<Client, Error> {
-        Client::login(&self.server, &self.login_name, username, password).await
+        let servers = self.candidate_servers();
+        let servers: Vec<&str> = servers.iter().map(String::as_str).collect();
+        let result =
+            Client::login_with_fallback(&servers, &self.login_name, username, password).await;
+        match result {
+            Err(Error::Rpc(err))
+                if err.code == jsonrpc::ErrorCode::InvalidSchoolName.as_isize() =>
+            {
+                Err(Error::SchoolMoved {
+                    login_name: self.login_name.clone(),
+                })
+            }
+            result => result,
+        }
+    }
+}
+
+pub(crate) async fn fetch_timetable<T: DeserializeOwned + LessonDate>(
+    rpc_client: &jsonrpc::Client,
+    options: &TimetableOptions,
+    week_start: Weekday,
+    element: &ElementRef,
+    start_date: &Date,
+    end_date: &Date,
+) -> Result<Vec<T>, Error> {
+    let (first_date, last_date) = requested_range(options, week_start, start_date, end_date);
+
+    let mut lessons = Vec::new();
+    for (start, end) in first_date.split_range(&last_date, options.max_span_days) {
+        let chunk = fetch_timetable_range(rpc_client, options, element, &start, &end);
+        lessons.extend(chunk.await?);
+    }
+    if options.align_to_weeks {
+        retain_range(&mut lessons, start_date, end_date);
+    }
+    Ok(lessons)
+}
+
+/// Returns the range that is actually requested for the range from `start_date` to `end_date`, which is widened to
+/// whole weeks starting on `week_start` if [`align_to_weeks`](TimetableOptions::align_to_weeks) is set.
+fn requested_range(
+    options: &TimetableOptions,
+    week_start: Weekday,
+    start_date: &Date,
+    end_date: &Date,
+) -> (Date, Date) {
+    if options.align_to_weeks {
+        (
+            start_date.relative_week_begin_on(week_start),
+            end_date.relative_week_end_on(week_start),
+        )
+    } else {
+        (*start_date, *end_date)
+    }
+}
+
+/// Splits the range from `start_date` to `end_date`, both inclusive, wherever a schoolyear starts or ends.
+pub(crate) fn split_at_schoolyears(
+    start_date: &Date,
+    end_date: &Date,
+    schoolyears: &[Schoolyear],
+) -> Vec<(Date, Date)> {
+    let mut cuts: Vec<chrono::NaiveDate> = schoolyears
+        .iter()
+        .flat_map(|schoolyear| {
+            [
+                Some(schoolyear.start_date.0),
+                schoolyear.end_date.succ_opt(),
+            ]
+        })
+        .flatten()
+        .filter(|cut| start_date.0 < *cut && *cut <= end_date.0)
+        .collect();
+    cuts.sort();
+    cuts.dedup();
+
+    let mut ranges = Vec::new();
+    let mut start = *start_date;
+    for cut in cuts {
+        if let Some(end) = cut.pred_opt() {
+            ranges.push((start, Date(end)));
+        }
+        start = Date(cut);
+    }
+    ranges.push((start, *end_date));
+    ranges
+}
+
+/// Anything that a timetable can be fetched as, either parsed lessons or raw JSON values.
+pub(crate) trait LessonDate {
+    /// Returns the date of the lesson, `None` if it is malformed.
+    fn lesson_date(&self) -> Option<Date>;
+}
+
+impl LessonDate for Lesson {
+    fn lesson_date(&self) -> Option<Date> {
+        Some(self.date)
+    }
+}
+
+impl LessonDate for serde_json::Value {
+    fn lesson_date(&self) -> Option<Date> {
+        serde_json::from_value(self.get("date")?.clone()).ok()
+    }
+}
+
+/// Drops the lessons outside of the range from `start_date` to `end_date`, both inclusive.
+/// Malformed lessons are kept, so that they are still reported by the tolerant helpers.
+fn retain_range<T: LessonDate>(lessons: &mut Vec<T>, start_date: &Date, end_date: &Date) {
+    lessons.retain(|lesson| match lesson.lesson_date() {
+        Some(date) => *start_date <= date && date <= *end_date,
+        None => true,
+    });
+}
+
+/// Some schools reject optional fields instead of ignoring them, so a request with invalid params is sent once more
+/// without any optional fields. Both requests show up in a [request log](Client::record_requests).
+async fn fetch_timetable_range<T: DeserializeOwned>(
+    rpc_client: &jsonrpc::Client,
+    options: &TimetableOptions,
+    element: &ElementRef,
+    start_date: &Date,
+    end_date: &Date,
+) -> Result<Vec<T>, Error> {
+    without_optional_fields_on_rejection(options, |options| async move {
+        request_timetable_range(rpc_client, &options, element, start_date, end_date).await
+    })
+    .await
+}
+
+async fn without_optional_fields_on_rejection<T, F, Fut>(
+    options: &TimetableOptions,
+    request: F,
+) -> Result<T, Error>
+where
+    F: Fn(TimetableOptions) -> Fut,
+    Fut: std::future::Future<Output = Result<T, Error>>,
+{
+    let minimal = options.without_optional_fields();
+    match request(*options).await {
+        Err(Error::Rpc(err))
+            if err.code == jsonrpc::ErrorCode::InvalidParams.as_isize() && minimal != *options =>
+        {
+            request(minimal).await
+        }
+        result => result,
+    }
+}
+
+async fn request_timetable_range<T: DeserializeOwned>(
+    rpc_client: &jsonrpc::Client,
+    options: &TimetableOptions,
+    element: &ElementRef,
+    start_date: &Date,
+    end_date: &Date,
+) -> Result<Vec<T>, Error> {
+    let params = params::TimetableParams {
+        options: &params::TimetableParamsOpts {
+            element: &params::TimetableParamsElem::from(element),
+            start_date,
+            end_date,
+            show_booking: &options.show_booking,
+            show_info: &options.show_info,
+            show_subst_text: &options.show_subst_text,
+            show_ls_text: &options.show_ls_text,
+            show_ls_number: &true,
+            show_student_group: &options.show_student_group,
+            class_fields: &["id", "name"],
+            room_fields: &["id", "name"],
+            subject_fields: &["id", "name"],
+            teacher_fields: &["id", "name"],
+            department_id: options.department_id.as_ref(),
+        },
+    };
+    rpc_client.request("getTimetable", params).await
+}
+
+pub(crate) async fn fetch_timetables(
+    rpc_client: &jsonrpc::Client,
+    options: &TimetableOptions,
+    week_start: Weekday,
+    elements: &[ElementRef],
+    start_date: &Date,
+    end_date: &Date,
+) -> Result<Timetables, Error> {
+    let entries = stream::iter(elements)
+        .map(|element| async move {
+            let lessons = fetch_timetable(
+                rpc_client, options, week_start, element, start_date, end_date,
+            )
+            .await?;
+            Ok::<_, Error>((element.clone(), lessons))
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+    Ok(Timetables { entries })
+}
+
+/// The only fields of a student that [`Client::student_names()`] parses.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StudentName {
+    id: usize,
+    fore_name: String,
+    long_name: String,
+}
+
+/// Treats a list that the user isn't allowed to access as empty.
+pub(crate) fn allow_no_access<T>(result: Result<Vec<T>, Error>) -> Result<Vec<T>, Error> {
+    match result {
+        Err(err) if is_no_access(&err) => Ok(Vec::new()),
+        result => result,
+    }
+}
+
+/// Tells whether a list could be fetched, treating a denied access as `false`.
+fn is_listable<T>(result: Result<Vec<T>, Error>) -> Result<bool, Error> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(err) if is_no_access(&err) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn is_no_access(err: &Error) -> bool {
+    matches!(err, Error::Rpc(err) if err.code == jsonrpc::ErrorCode::NoAccess.as_isize())
+}
+
+fn sorted(lessons: Result<Vec<Lesson>, Error>) -> Result<Vec<Lesson>, Error> {
+    let mut lessons = lessons?;
+    sort_lessons(&mut lessons);
+    Ok(lessons)
+}
+
+fn env_var(name: &'static str) -> Result<String, Error> {
+    match std::env::var(name) {
+        Ok(value) if !value.is_empty() => Ok(value),
+        _ => Err(Error::MissingEnvVar(name)),
+    }
+}
+
+fn next_lesson(lessons: Vec<Lesson>, now: chrono::NaiveDateTime) -> Option<Lesson> {
+    lessons
+        .into_iter()
+        .filter(|lesson| lesson.code != LessonCode::Cancelled && lesson.start_datetime() >= now)
+        .min_by_key(Lesson::start_datetime)
+}
+
+fn find_conflicts(lessons: &[Lesson]) -> Vec<(Lesson, Lesson)> {
+    let lessons: Vec<&Lesson> = lessons
+        .iter()
+        .filter(|lesson| lesson.code != LessonCode::Cancelled)
+        .collect();
+
+    let mut conflicts = Vec::new();
+    for (index, first) in lessons.iter().enumerate() {
+        for second in &lessons[index + 1..] {
+            if first.lsnumber != second.lsnumber && first.conflicts_with(second) {
+                conflicts.push(((*first).clone(), (*second).clone()));
+            }
+        }
     }
+    conflicts
+}
+
+/// Timestamps below this are in seconds, above it in milliseconds.
+/// As seconds it is in the year 5138, as milliseconds in 1973, so neither unit is plausible on the other side.
+const SECONDS_TIMESTAMP_LIMIT: i64 = 100_000_000_000;
+
+/// Converts a Untis timestamp, which most endpoints send in milliseconds and some in seconds.
+pub(crate) fn timestamp_from_untis(ts: i64) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+    let time = if ts.unsigned_abs() < SECONDS_TIMESTAMP_LIMIT as u64 {
+        chrono::Utc.timestamp_opt(ts, 0)
+    } else {
+        chrono::Utc.timestamp_millis_opt(ts)
+    };
+    time.single().ok_or(Error::InvalidTimestamp(ts))
 }
 
 fn make_untis_url(server: &str, school: &str) -> String {
     format!("https://{}/WebUntis/jsonrpc.do?school={}", server, school)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn lesson(id: usize, lsnumber: usize, start_time: u16, end_time: u16) -> Lesson {
+        test_util::lesson(id)
+            .lsnumber(lsnumber)
+            .time(start_time, end_time)
+            .room(1, "R1")
+            .build()
+    }
+
+    #[test]
+    fn aligned_ranges_use_the_week_start() {
+        let date = |d| Date::from_ymd_opt(2024, 5, d).unwrap();
+        let aligned = TimetableOptions {
+            align_to_weeks: true,
+            ..TimetableOptions::default()
+        };
+        // 2024-05-08 is a wednesday, 2024-05-12 a sunday.
+        assert_eq!(
+            requested_range(&aligned, Weekday::Mon, &date(8), &date(8)),
+            (date(6), date(11))
+        );
+        assert_eq!(
+            requested_range(&aligned, Weekday::Sun, &date(8), &date(8)),
+            (date(5), date(10))
+        );
+        assert_eq!(
+            requested_range(&aligned, Weekday::Sun, &date(8), &date(12)),
+            (date(5), date(17))
+        );
+        assert_eq!(
+            requested_range(
+                &TimetableOptions::default(),
+                Weekday::Sun,
+                &date(8),
+                &date(9)
+            ),
+            (date(8), date(9))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_optional_fields_are_dropped() {
+        let requests = std::sync::Mutex::new(Vec::new());
+        // A school that doesn't accept booking info.
+        let result =
+            without_optional_fields_on_rejection(&TimetableOptions::default(), |options| {
+                requests.lock().unwrap().push(options);
+                async move {
+                    if options.show_booking {
+                        Err(Error::Rpc(jsonrpc::Error {
+                            code: -32602,
+                            message: String::from("invalid option showBooking"),
+                        }))
+                    } else {
+                        Ok(Vec::<Lesson>::new())
+                    }
+                }
+            })
+            .await;
+
+        assert!(result.is_ok());
+        let requests = requests.into_inner().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[1].show_booking && !requests[1].show_student_group);
+        assert_eq!(requests[1].max_span_days, 31);
+    }
+
+    #[test]
+    fn ranges_are_split_at_schoolyear_boundaries() {
+        let date = |y, m, d| Date::from_ymd_opt(y, m, d).unwrap();
+        let schoolyear = |id, start, end| Schoolyear {
+            id,
+            name: String::new(),
+            start_date: start,
+            end_date: end,
+        };
+        let schoolyears = [
+            schoolyear(1, date(2023, 9, 4), date(2024, 8, 31)),
+            schoolyear(2, date(2024, 9, 1), date(2025, 8, 31)),
+        ];
+
+        assert_eq!(
+            split_at_schoolyears(&date(2024, 8, 26), &date(2024, 9, 6), &schoolyears),
+            vec![
+                (date(2024, 8, 26), date(2024, 8, 31)),
+                (date(2024, 9, 1), date(2024, 9, 6)),
+            ]
+        );
+        assert_eq!(
+            split_at_schoolyears(&date(2024, 5, 6), &date(2024, 5, 10), &schoolyears),
+            vec![(date(2024, 5, 6), date(2024, 5, 10))]
+        );
+        assert_eq!(
+            split_at_schoolyears(&date(2023, 8, 28), &date(2023, 9, 8), &[]),
+            vec![(date(2023, 8, 28), date(2023, 9, 8))]
+        );
+    }
+
+    #[test]
+    fn aligned_timetables_are_filtered_to_the_requested_range() {
+        let date = |d| Date::from_ymd_opt(2024, 5, d).unwrap();
+        let mut lessons = vec![
+            serde_json::json!({ "id": 1, "date": 20240506 }),
+            serde_json::json!({ "id": 2, "date": 20240508 }),
+            serde_json::json!({ "id": 3, "date": 20240510 }),
+            serde_json::json!({ "id": 4 }),
+        ];
+        retain_range(&mut lessons, &date(8), &date(10));
+        let ids = lessons
+            .iter()
+            .map(|lesson| lesson["id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3, 4]);
+
+        let mut lessons = vec![lesson(1, 100, 800, 850)];
+        retain_range(&mut lessons, &date(7), &date(10));
+        assert!(lessons.is_empty());
+    }
+
+    #[test]
+    fn next_lesson_skips_past_and_cancelled_lessons() {
+        let mut cancelled = lesson(3, 300, 1000, 1050);
+        cancelled.code = LessonCode::Cancelled;
+        let lessons = vec![
+            lesson(4, 400, 1100, 1150),
+            lesson(1, 100, 800, 850),
+            cancelled,
+            lesson(2, 200, 900, 950),
+        ];
+        let at = |time: &str| {
+            chrono::NaiveDateTime::parse_from_str(&format!("2024-05-06 {}", time), "%Y-%m-%d %H:%M")
+                .unwrap()
+        };
+
+        assert_eq!(
+            next_lesson(lessons.clone(), at("08:30")).map(|l| l.id),
+            Some(2)
+        );
+        assert_eq!(
+            next_lesson(lessons.clone(), at("09:00")).map(|l| l.id),
+            Some(2)
+        );
+        assert_eq!(
+            next_lesson(lessons.clone(), at("09:30")).map(|l| l.id),
+            Some(4)
+        );
+        assert_eq!(next_lesson(lessons, at("11:30")), None);
+    }
+
+    #[test]
+    fn find_conflicts_reports_overlapping_lessons() {
+        let lessons = vec![
+            lesson(1, 100, 800, 850),
+            lesson(2, 200, 830, 920),
+            lesson(3, 300, 920, 1010),
+        ];
+        let conflicts = find_conflicts(&lessons);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].0.id, conflicts[0].1.id), (1, 2));
+    }
+
+    #[test]
+    fn find_conflicts_ignores_parallel_groups_and_cancellations() {
+        let mut cancelled = lesson(3, 300, 800, 850);
+        cancelled.code = LessonCode::Cancelled;
+        let lessons = vec![
+            lesson(1, 100, 800, 850),
+            lesson(2, 100, 800, 850),
+            cancelled,
+        ];
+
+        assert!(find_conflicts(&lessons).is_empty());
+    }
+
+    #[test]
+    fn timestamp_from_untis_converts_milliseconds() {
+        let time = timestamp_from_untis(1_700_000_000_000).unwrap();
+        assert_eq!(time.timestamp(), 1_700_000_000);
+    }
+
+    #[test]
+    fn timestamp_from_untis_detects_seconds() {
+        let time = timestamp_from_untis(1_700_000_000).unwrap();
+        assert_eq!(time.timestamp(), 1_700_000_000);
+    }
+
+    #[test]
+    fn timestamp_from_untis_rejects_extreme_timestamp() {
+        assert!(matches!(
+            timestamp_from_untis(i64::MAX),
+            Err(Error::InvalidTimestamp(i64::MAX))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+
+/// A color used for displaying timetable elements, sent by Untis as a hex string like `ff0000`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    /// Parses a color formatted as `RRGGBB` or `#RRGGBB`, returns `None` for anything else.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+        Some(Self {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+
+    /// Returns the red, green and blue channels.
+    pub fn as_rgb(&self) -> (u8, u8, u8) {
+        (self.r, self.g, self.b)
+    }
+
+    /// Formats the color as `rrggbb`, like Untis does. Prepend a `#` for CSS.
+    pub fn to_hex(&self) -> String {
+        format!("{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "#{}", self.to_hex())
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Color::from_hex(&value)
+            .ok_or_else(|| de::Error::custom(format!("invalid color: {}", value)))
+    }
+}
+
+/// Deserializes an optional color, treating empty strings like absent values, as Untis sends both.
+/// Malformed colors are treated as absent as well, so that a single one doesn't fail a whole list.
+pub(crate) fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| Color::from_hex(value.trim())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_with_and_without_hash() {
+        let red = Color { r: 255, g: 0, b: 0 };
+        assert_eq!(Color::from_hex("ff0000"), Some(red));
+        assert_eq!(Color::from_hex("#FF0000"), Some(red));
+        assert_eq!(Color::from_hex("f49f25").unwrap().as_rgb(), (244, 159, 37));
+        assert_eq!(red.to_hex(), "ff0000");
+        assert_eq!(red.to_string(), "#ff0000");
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for hex in [
+            "", "#", "fff", "ff00000", "gg0000", "#ff00", "+f0000", "ff 000", "ä0000",
+        ] {
+            assert_eq!(Color::from_hex(hex), None, "{:?}", hex);
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Colored {
+        #[serde(default, deserialize_with = "deserialize_optional")]
+        color: Option<Color>,
+    }
+
+    #[test]
+    fn optional_colors_may_be_empty_or_absent() {
+        let parse = |json| serde_json::from_value::<Colored>(json).map(|colored| colored.color);
+        assert_eq!(parse(serde_json::json!({})).unwrap(), None);
+        assert_eq!(parse(serde_json::json!({ "color": null })).unwrap(), None);
+        assert_eq!(parse(serde_json::json!({ "color": "" })).unwrap(), None);
+        assert_eq!(
+            parse(serde_json::json!({ "color": "00ff00" })).unwrap(),
+            Color::from_hex("00ff00")
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "color": "green" })).unwrap(),
+            None
+        );
+    }
+}
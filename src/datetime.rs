@@ -1,7 +1,9 @@
-use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime};
-use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveTime, Timelike, Weekday};
+use serde::{self, de, ser, Deserialize, Deserializer, Serialize, Serializer};
 use std::ops::Deref;
 
+use crate::error::Error;
+
 /// Wrapper around chrono::NaiveDate for working with Untis more easily.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct Date(pub NaiveDate);
@@ -12,29 +14,126 @@ impl Date {
         Date(Local::now().date_naive())
     }
 
+    /// Creates a date from a year, month and day, or returns `None` if that date doesn't exist (e.g. February 30).
+    pub fn from_ymd_opt(year: i32, month: u32, day: u32) -> Option<Self> {
+        NaiveDate::from_ymd_opt(year, month, day).map(Date)
+    }
+
+    /// Like [`from_ymd_opt()`](Self::from_ymd_opt), but returns [`Error::InvalidDate`] for dates that don't exist.
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Result<Self, Error> {
+        Self::from_ymd_opt(year, month, day).ok_or(Error::InvalidDate { year, month, day })
+    }
+
     /// Returns the last start of the week (monday).
     pub fn current_week_begin() -> Self {
         Self::today().relative_week_begin()
     }
 
-    /// Returns the next end of the week (saturday).
+    /// Returns the next end of the week (saturday), or today itself on the seventh day of the week (sunday).
+    /// See [`relative_week_end()`](Self::relative_week_end).
     pub fn current_week_end() -> Self {
         Self::today().relative_week_end()
     }
 
     /// Returns the last start of the week before this date.
+    /// Never panics; at the lower limit of representable dates, the date itself is returned.
     pub fn relative_week_begin(&self) -> Self {
-        let days_from_monday = self.weekday().num_days_from_monday();
-        let monday = self.0 - Duration::days(days_from_monday as i64);
-        Date(monday)
+        self.relative_week_begin_on(Weekday::Mon)
     }
 
     /// Returns the next end of the week (saturday) after this date.
+    /// Sundays belong to the week before them, but a week never ends before the date itself, so this returns the
+    /// sunday itself for a sunday.
+    /// Never panics; at the upper limit of representable dates, the date itself is returned.
     pub fn relative_week_end(&self) -> Self {
-        let days_from_monday = self.weekday().num_days_from_monday() as i64;
-        let days_left_till_saturday = 5 - days_from_monday;
-        let saturday = self.0 + Duration::days(days_left_till_saturday);
-        Date(saturday)
+        self.relative_week_end_on(Weekday::Mon)
+    }
+
+    /// Like [`relative_week_begin()`](Self::relative_week_begin), but for schools whose week starts on `first_day`.
+    pub fn relative_week_begin_on(&self, first_day: Weekday) -> Self {
+        let days_since_begin = self.weekday().days_since(first_day) as u64;
+        let begin = self.0.checked_sub_days(Days::new(days_since_begin));
+        Date(begin.unwrap_or(self.0))
+    }
+
+    /// Like [`relative_week_end()`](Self::relative_week_end), but for schools whose week starts on `first_day`.
+    /// Weeks are six days long, so for a week starting on sunday, this returns the friday. For the seventh day, the
+    /// date itself is returned, so that a week's range always contains the date it was computed from.
+    pub fn relative_week_end_on(&self, first_day: Weekday) -> Self {
+        let begin = self.relative_week_begin_on(first_day);
+        let end = begin.0.checked_add_days(Days::new(5)).unwrap_or(self.0);
+        Date(end.max(self.0))
+    }
+
+    /// Returns the first and last day of the week that this date is in, both inclusive, for schools whose week starts
+    /// on `first_day`. These are the dates that the week helpers of the client request.
+    pub fn week_bounds(&self, first_day: Weekday) -> (Self, Self) {
+        (
+            self.relative_week_begin_on(first_day),
+            self.relative_week_end_on(first_day),
+        )
+    }
+
+    /// Splits the range from `self` to `end`, both inclusive, into consecutive ranges of at most `max_days` days.
+    /// A `max_days` of 0 doesn't split the range at all.
+    pub fn split_range(&self, end: &Date, max_days: u32) -> Vec<(Date, Date)> {
+        if max_days == 0 || self.0 > end.0 {
+            return vec![(*self, *end)];
+        }
+
+        let mut ranges = Vec::new();
+        let mut start = self.0;
+        loop {
+            let chunk_end = start
+                .checked_add_days(Days::new(u64::from(max_days) - 1))
+                .map_or(end.0, |date| date.min(end.0));
+            ranges.push((Date(start), Date(chunk_end)));
+            match chunk_end.succ_opt() {
+                Some(next) if chunk_end < end.0 => start = next,
+                _ => return ranges,
+            }
+        }
+    }
+
+    /// Returns the ISO 8601 week number (1-53) of this date.
+    pub fn iso_week(&self) -> u32 {
+        self.0.iso_week().week()
+    }
+
+    /// Returns the week number within a schoolyear that starts at `schoolyear_start`.
+    /// The week containing the start is week 1, weeks begin on monday. Dates before the start return 0.
+    ///
+    /// For timetables alternating between week A and B, `academic_week() % 2 == 1` is week A.
+    pub fn academic_week(&self, schoolyear_start: &Date) -> u32 {
+        let first_monday = schoolyear_start.relative_week_begin();
+        if self.0 < first_monday.0 {
+            return 0;
+        }
+        let days = (self.0 - first_monday.0).num_days();
+        (days / 7) as u32 + 1
+    }
+
+    /// Returns the English name of this date's weekday, e.g. `Monday`.
+    pub fn weekday_name(&self) -> &'static str {
+        match self.weekday() {
+            Weekday::Mon => "Monday",
+            Weekday::Tue => "Tuesday",
+            Weekday::Wed => "Wednesday",
+            Weekday::Thu => "Thursday",
+            Weekday::Fri => "Friday",
+            Weekday::Sat => "Saturday",
+            Weekday::Sun => "Sunday",
+        }
+    }
+
+    /// Formats this date for compact displays, e.g. `Mon 06 May`.
+    pub fn short_date(&self) -> String {
+        self.format("%a %d %b").to_string()
+    }
+
+    /// Formats this date as ISO 8601, e.g. `2024-05-06`.
+    pub fn iso(&self) -> String {
+        self.format("%Y-%m-%d").to_string()
     }
 
     /// Returns the inner `NaiveDate`.
@@ -49,6 +148,12 @@ impl From<NaiveDate> for Date {
     }
 }
 
+impl From<Date> for NaiveDate {
+    fn from(value: Date) -> Self {
+        value.0
+    }
+}
+
 impl Deref for Date {
     type Target = NaiveDate;
 
@@ -59,30 +164,55 @@ impl Deref for Date {
 
 impl Serialize for Date {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.serialize_u32(chrono_to_untis_date(**self))
+        let value = chrono_to_untis_date(**self)
+            .ok_or_else(|| ser::Error::custom(format!("date out of range: {}", self.0)))?;
+        serializer.serialize_u32(value)
     }
 }
 
 impl<'de> Deserialize<'de> for Date {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Date, D::Error> {
-        Ok(Date(chrono_from_untis_date(u32::deserialize(
-            deserializer,
-        )?)))
+        let value = u32::deserialize(deserializer)?;
+        let date = chrono_from_untis_date(value)
+            .ok_or_else(|| de::Error::custom(format!("invalid date: {}", value)))?;
+        Ok(Date(date))
     }
 }
 
-fn chrono_to_untis_date(date: NaiveDate) -> u32 {
-    let string = format!("{}", date.format("%Y%m%d"));
-    string.parse::<u32>().unwrap()
+/// Untis dates are formatted as `YYYYMMDD`, so only years from 0 to 9999 can be represented.
+fn chrono_to_untis_date(date: NaiveDate) -> Option<u32> {
+    let year = u32::try_from(date.year())
+        .ok()
+        .filter(|year| *year <= 9999)?;
+    Some(year * 10000 + date.month() * 100 + date.day())
 }
 
-fn chrono_from_untis_date(value: u32) -> NaiveDate {
-    let string = format!("{}", value);
-    let year = string[0..4].parse::<i32>().unwrap();
-    let month = string[4..6].parse::<u32>().unwrap();
-    let day = string[6..8].parse::<u32>().unwrap();
+fn chrono_from_untis_date(value: u32) -> Option<NaiveDate> {
+    let year = (value / 10000) as i32;
+    let month = value / 100 % 100;
+    let day = value % 100;
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
 
-    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+/// Generates the dates that Untis can represent, i.e. real calendar dates from the years 0 to 9999.
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Date {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+
+        let days = |year| {
+            NaiveDate::from_ymd_opt(year, 1, 1)
+                .unwrap()
+                .num_days_from_ce()
+        };
+        (days(0)..days(10000))
+            .prop_map(|days| Date(NaiveDate::from_num_days_from_ce_opt(days).unwrap()))
+            .boxed()
+    }
 }
 
 /// Wrapper around chrono::NaiveDate for working with Untis more easily.
@@ -101,6 +231,12 @@ impl From<NaiveTime> for Time {
     }
 }
 
+impl From<Time> for NaiveTime {
+    fn from(value: Time) -> Self {
+        value.0
+    }
+}
+
 impl Deref for Time {
     type Target = NaiveTime;
 
@@ -117,37 +253,226 @@ impl Serialize for Time {
 
 impl<'de> Deserialize<'de> for Time {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Time, D::Error> {
-        Ok(Time(chrono_from_untis_time(u16::deserialize(
-            deserializer,
-        )?)))
+        let value = u16::deserialize(deserializer)?;
+        let time = chrono_from_untis_time(value)
+            .ok_or_else(|| de::Error::custom(format!("invalid time: {}", value)))?;
+        Ok(Time(time))
+    }
+}
+
+/// Generates the times of a day in whole minutes, as Untis sends them.
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Time {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+
+        (0..24 * 60u32)
+            .prop_map(|minutes| {
+                Time(NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0).unwrap())
+            })
+            .boxed()
     }
 }
 
+/// Untis times are formatted as `HHMM` without leading zeros, seconds are dropped.
 fn chrono_to_untis_time(time: NaiveTime) -> u16 {
-    let string = format!("{}", time.format("%k%M"));
-    let number = string.trim().parse::<u16>().unwrap();
-    number
+    (time.hour() * 100 + time.minute()) as u16
+}
+
+fn chrono_from_untis_time(value: u16) -> Option<NaiveTime> {
+    let hours = (value / 100) as u32;
+    let mins = (value % 100) as u32;
+    NaiveTime::from_hms_opt(hours, mins, 0)
+}
+
+/// Serializes a [`Date`] or `NaiveDate` field as an Untis integer like `20240506`, which is also the default format
+/// of [`Date`]. Use with `#[serde(with = "untis::serde_untis_date")]`, e.g. for chrono dates that are sent back to
+/// Untis.
+pub mod serde_untis_date {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<NaiveDate>,
+        S: Serializer,
+    {
+        Date((*value).into()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<NaiveDate>,
+        D: Deserializer<'de>,
+    {
+        Ok(T::from(Date::deserialize(deserializer)?.0))
+    }
+}
+
+/// Serializes a [`Date`] or `NaiveDate` field as an ISO 8601 string like `"2024-05-06"`.
+/// Use with `#[serde(with = "untis::serde_iso_date")]`, e.g. when exporting lessons to other systems.
+pub mod serde_iso_date {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<NaiveDate>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&Date((*value).into()).iso())
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<NaiveDate>,
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+            .map_err(|_| de::Error::custom(format!("invalid date: {}", value)))?;
+        Ok(T::from(date))
+    }
+}
+
+/// Serializes a [`Time`] or `NaiveTime` field as an Untis integer like `800`, which is also the default format of
+/// [`Time`]. Use with `#[serde(with = "untis::serde_untis_time")]`. Seconds are dropped.
+pub mod serde_untis_time {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<NaiveTime>,
+        S: Serializer,
+    {
+        Time((*value).into()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<NaiveTime>,
+        D: Deserializer<'de>,
+    {
+        Ok(T::from(Time::deserialize(deserializer)?.0))
+    }
 }
 
-fn chrono_from_untis_time(value: u16) -> NaiveTime {
-    let string = value.to_string();
-    let hour_len = if string.len() == 3 { 1 } else { 2 };
-    let hours = string[0..hour_len].parse::<u32>().unwrap();
-    let mins = string[hour_len..(hour_len + 2)].parse::<u32>().unwrap();
-    NaiveTime::from_hms_opt(hours, mins, 0).unwrap()
+/// Serializes a [`Time`] or `NaiveTime` field as an ISO 8601 string like `"08:00"`.
+/// Use with `#[serde(with = "untis::serde_iso_time")]`. Seconds are dropped, but accepted when deserializing.
+pub mod serde_iso_time {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<NaiveTime>,
+        S: Serializer,
+    {
+        let time: NaiveTime = (*value).into();
+        serializer.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<NaiveTime>,
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        let time = NaiveTime::parse_from_str(&value, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(&value, "%H:%M:%S"))
+            .map_err(|_| de::Error::custom(format!("invalid time: {}", value)))?;
+        Ok(T::from(time))
+    }
 }
 
 #[cfg(test)]
 #[allow(clippy::zero_prefixed_literal)]
 mod tests {
     use super::*;
-    use chrono::Weekday;
+
+    #[test]
+    fn serde_modules_select_the_format() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Export {
+            #[serde(with = "serde_iso_date")]
+            date: Date,
+            #[serde(with = "serde_iso_time")]
+            start: Time,
+            #[serde(with = "serde_untis_date")]
+            chrono_date: NaiveDate,
+            #[serde(with = "serde_untis_time")]
+            chrono_time: NaiveTime,
+        }
+
+        let export = Export {
+            date: Date(NaiveDate::from_ymd_opt(2024, 05, 06).unwrap()),
+            start: Time(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+            chrono_date: NaiveDate::from_ymd_opt(2024, 05, 06).unwrap(),
+            chrono_time: NaiveTime::from_hms_opt(8, 5, 0).unwrap(),
+        };
+        let json = serde_json::json!({
+            "date": "2024-05-06",
+            "start": "08:00",
+            "chrono_date": 20240506,
+            "chrono_time": 805,
+        });
+        assert_eq!(serde_json::to_value(&export).unwrap(), json);
+        assert_eq!(serde_json::from_value::<Export>(json).unwrap(), export);
+
+        let mut with_seconds = serde_json::to_value(&export).unwrap();
+        with_seconds["start"] = serde_json::json!("08:00:00");
+        assert_eq!(
+            serde_json::from_value::<Export>(with_seconds).unwrap(),
+            export
+        );
+        let mut invalid = serde_json::to_value(&export).unwrap();
+        invalid["date"] = serde_json::json!("2023-02-29");
+        assert!(serde_json::from_value::<Export>(invalid).is_err());
+    }
+
+    #[test]
+    fn from_ymd_rejects_invalid_dates() {
+        assert_eq!(
+            Date::from_ymd(2024, 2, 29).unwrap(),
+            Date::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert!(matches!(
+            Date::from_ymd(2023, 2, 29),
+            Err(Error::InvalidDate {
+                year: 2023,
+                month: 2,
+                day: 29
+            })
+        ));
+        assert!(Date::from_ymd(2024, 13, 1).is_err());
+    }
+
+    #[test]
+    fn split_range_into_chunks() {
+        let date = |y, m, d| Date::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            date(2024, 01, 01).split_range(&date(2024, 01, 10), 4),
+            vec![
+                (date(2024, 01, 01), date(2024, 01, 04)),
+                (date(2024, 01, 05), date(2024, 01, 08)),
+                (date(2024, 01, 09), date(2024, 01, 10)),
+            ]
+        );
+        assert_eq!(
+            date(2024, 01, 01).split_range(&date(2024, 01, 08), 4).len(),
+            2
+        );
+        assert_eq!(
+            date(2024, 01, 01).split_range(&date(2024, 12, 31), 0),
+            vec![(date(2024, 01, 01), date(2024, 12, 31))]
+        );
+    }
 
     #[test]
     fn convert_untis_date_forth_and_back() {
         let number = 20180316;
-        let date = chrono_from_untis_date(number);
-        let new_number = chrono_to_untis_date(date);
+        let date = chrono_from_untis_date(number).unwrap();
+        let new_number = chrono_to_untis_date(date).unwrap();
 
         assert_eq!(number, new_number);
     }
@@ -167,8 +492,34 @@ mod tests {
 
     #[test]
     fn untis_date_week_end_is_saturday() {
-        let saturday = Date::current_week_end();
-        assert_eq!(saturday.0.weekday(), Weekday::Sat);
+        let wednesday = Date(NaiveDate::from_ymd_opt(2023, 08, 30).unwrap());
+        assert_eq!(wednesday.relative_week_end().0.weekday(), Weekday::Sat);
+    }
+
+    #[test]
+    fn untis_date_week_bounds_contain_the_date() {
+        let date = |d| Date(NaiveDate::from_ymd_opt(2023, 09, d).unwrap());
+        // 2023-09-03 is a sunday, 2023-09-04 a monday.
+        let cases = [
+            (Weekday::Mon, date(4), (date(4), date(9))),
+            (Weekday::Mon, date(9), (date(4), date(9))),
+            (Weekday::Mon, date(10), (date(4), date(10))),
+            (Weekday::Sun, date(3), (date(3), date(8))),
+            (Weekday::Sun, date(8), (date(3), date(8))),
+            (Weekday::Sun, date(9), (date(3), date(9))),
+            (Weekday::Sat, date(9), (date(9), date(14))),
+            (Weekday::Sat, date(15), (date(9), date(15))),
+        ];
+        for (first_day, day, bounds) in cases {
+            assert_eq!(
+                day.week_bounds(first_day),
+                bounds,
+                "{:?} {}",
+                first_day,
+                day.iso()
+            );
+            assert!(bounds.0 <= day && day <= bounds.1);
+        }
     }
 
     #[test]
@@ -178,14 +529,111 @@ mod tests {
         assert_eq!(monday, date.relative_week_end());
     }
 
+    #[test]
+    fn untis_date_week_on_sunday_start() {
+        let date = Date(NaiveDate::from_ymd_opt(2023, 09, 01).unwrap());
+        let sunday = Date(NaiveDate::from_ymd_opt(2023, 08, 27).unwrap());
+        let friday = Date(NaiveDate::from_ymd_opt(2023, 09, 01).unwrap());
+        assert_eq!(date.relative_week_begin_on(Weekday::Sun), sunday);
+        assert_eq!(date.relative_week_end_on(Weekday::Sun), friday);
+        assert_eq!(sunday.relative_week_begin_on(Weekday::Sun), sunday);
+    }
+
+    #[test]
+    fn untis_date_iso_week() {
+        let date = Date(NaiveDate::from_ymd_opt(2023, 09, 01).unwrap());
+        assert_eq!(date.iso_week(), 35);
+
+        let date = Date(NaiveDate::from_ymd_opt(2021, 01, 01).unwrap());
+        assert_eq!(date.iso_week(), 53);
+    }
+
+    #[test]
+    fn untis_date_academic_week() {
+        // Friday, so the first week only has two school days.
+        let start = Date(NaiveDate::from_ymd_opt(2023, 09, 01).unwrap());
+
+        assert_eq!(start.academic_week(&start), 1);
+        let monday = Date(NaiveDate::from_ymd_opt(2023, 09, 04).unwrap());
+        assert_eq!(monday.academic_week(&start), 2);
+        let later = Date(NaiveDate::from_ymd_opt(2023, 10, 01).unwrap());
+        assert_eq!(later.academic_week(&start), 5);
+        let before = Date(NaiveDate::from_ymd_opt(2023, 08, 27).unwrap());
+        assert_eq!(before.academic_week(&start), 0);
+    }
+
+    #[test]
+    fn untis_date_display_helpers() {
+        let date = Date(NaiveDate::from_ymd_opt(2024, 05, 06).unwrap());
+        assert_eq!(date.weekday_name(), "Monday");
+        assert_eq!(date.short_date(), "Mon 06 May");
+        assert_eq!(date.iso(), "2024-05-06");
+    }
+
     #[test]
     fn convert_untis_time_forth_and_back() {
         let number = 830;
-        let time = chrono_from_untis_time(number);
+        let time = chrono_from_untis_time(number).unwrap();
         println!("Time: {}", time);
         let new_number = chrono_to_untis_time(time);
         println!("Num:  {}", new_number);
 
         assert_eq!(number, new_number);
     }
+
+    #[test]
+    fn untis_date_from_ymd_opt_rejects_invalid_dates() {
+        assert!(Date::from_ymd_opt(2024, 02, 29).is_some());
+        assert!(Date::from_ymd_opt(2023, 02, 29).is_none());
+        assert!(Date::from_ymd_opt(2023, 13, 01).is_none());
+    }
+
+    #[test]
+    fn untis_date_week_helpers_dont_panic_at_limits() {
+        assert_eq!(
+            Date(NaiveDate::MIN).relative_week_begin(),
+            Date(NaiveDate::MIN)
+        );
+        assert_eq!(
+            Date(NaiveDate::MAX).relative_week_end(),
+            Date(NaiveDate::MAX)
+        );
+    }
+
+    #[test]
+    fn deserialize_invalid_untis_date_fails() {
+        assert!(serde_json::from_str::<Date>("20231301").is_err());
+        assert!(serde_json::from_str::<Date>("7").is_err());
+    }
+
+    #[test]
+    fn serialize_out_of_range_untis_date_fails() {
+        let date = Date(NaiveDate::from_ymd_opt(10000, 01, 01).unwrap());
+        assert!(serde_json::to_string(&date).is_err());
+    }
+
+    #[test]
+    fn convert_untis_midnight_forth_and_back() {
+        let time = chrono_from_untis_time(0).unwrap();
+        assert_eq!(time, NaiveTime::from_hms_opt(0, 0, 0).unwrap());
+        assert_eq!(chrono_to_untis_time(time), 0);
+    }
+
+    #[test]
+    fn deserialize_invalid_untis_time_fails() {
+        assert!(serde_json::from_str::<Time>("2460").is_err());
+        assert!(serde_json::from_str::<Time>("875").is_err());
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn arbitrary_dates_and_times_survive_serialization(
+            date in proptest::arbitrary::any::<Date>(),
+            time in proptest::arbitrary::any::<Time>(),
+        ) {
+            let json = serde_json::to_string(&(date, time)).unwrap();
+            proptest::prop_assert_eq!(serde_json::from_str::<(Date, Time)>(&json).unwrap(), (date, time));
+        }
+    }
 }
@@ -0,0 +1,283 @@
+use crate::resources::{IdItem, Lesson, LessonCode};
+use std::collections::HashMap;
+
+/// Differences between two versions of a timetable, as computed by [`diff_timetables()`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct TimetableDiff {
+    /// Lessons that are new in the current timetable.
+    pub added: Vec<Lesson>,
+
+    /// Lessons that are no longer part of the current timetable.
+    pub removed: Vec<Lesson>,
+
+    /// Lessons that are part of both timetables, but have changed.
+    pub changed: Vec<LessonChange>,
+}
+
+impl TimetableDiff {
+    /// Returns whether both timetables were the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A lesson that has changed between two versions of a timetable.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LessonChange {
+    /// The lesson as it was in the previous timetable.
+    pub before: Lesson,
+
+    /// The lesson as it is in the current timetable.
+    pub after: Lesson,
+
+    /// What has changed, never empty.
+    pub kinds: Vec<ChangeKind>,
+}
+
+/// The different ways in which a lesson can change.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ChangeKind {
+    /// The lesson's date, start or end time has changed.
+    Moved,
+
+    /// The lesson takes place in different rooms.
+    Rooms,
+
+    /// The lesson is taught by different teachers.
+    Teachers,
+
+    /// Different subjects are taught in the lesson.
+    Subjects,
+
+    /// The lesson has been cancelled.
+    Cancelled,
+
+    /// The lesson was cancelled before, but takes place again.
+    Reinstated,
+
+    /// The lesson's info or substitution text has changed.
+    Text,
+}
+
+/// Compares two versions of a timetable, e.g. a persisted snapshot and a freshly fetched one.
+///
+/// Lessons are identified by their [`id`](Lesson::id), which stays the same when Untis updates a lesson.
+/// Both timetables should cover the same date range, otherwise lessons outside the current range show up as removed.
+pub fn diff_timetables(previous: &[Lesson], current: &[Lesson]) -> TimetableDiff {
+    let previous_by_id: HashMap<usize, &Lesson> =
+        previous.iter().map(|lesson| (lesson.id, lesson)).collect();
+    let current_by_id: HashMap<usize, &Lesson> =
+        current.iter().map(|lesson| (lesson.id, lesson)).collect();
+
+    let mut diff = TimetableDiff::default();
+    for lesson in current {
+        match previous_by_id.get(&lesson.id) {
+            None => diff.added.push(lesson.clone()),
+            Some(before) => {
+                let kinds = change_kinds(before, lesson);
+                if !kinds.is_empty() {
+                    diff.changed.push(LessonChange {
+                        before: (*before).clone(),
+                        after: lesson.clone(),
+                        kinds,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed = previous
+        .iter()
+        .filter(|lesson| !current_by_id.contains_key(&lesson.id))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Reconstructs how a timetable was originally planned, before substitutions, cancellations and extra lessons.
+///
+/// Untis marks every deviation from the plan in the lessons themselves: substituted elements carry the id of the
+/// original one in [`orgid`](IdItem::orgid), cancelled lessons have [`LessonCode::Cancelled`] and extra or moved
+/// lessons [`LessonCode::Irregular`]. Untis marks substitutions as irregular as well, so irregular lessons are only
+/// dropped if none of their elements was substituted, the others are restored like regular ones. Restored original
+/// elements get their name from [`orgname`](IdItem::orgname), it is left empty if the server didn't send it.
+/// Diffing the result against the timetable itself shows what is different about that specific week.
+pub fn planned_timetable(lessons: &[Lesson]) -> Vec<Lesson> {
+    lessons
+        .iter()
+        .filter(|lesson| lesson.code != LessonCode::Irregular || is_substitution(lesson))
+        .map(|lesson| Lesson {
+            code: LessonCode::Regular,
+            subst_text: None,
+            classes: original_elements(&lesson.classes),
+            subjects: original_elements(&lesson.subjects),
+            rooms: original_elements(&lesson.rooms),
+            teachers: original_elements(&lesson.teachers),
+            ..lesson.clone()
+        })
+        .collect()
+}
+
+fn is_substitution(lesson: &Lesson) -> bool {
+    [
+        &lesson.classes,
+        &lesson.subjects,
+        &lesson.rooms,
+        &lesson.teachers,
+    ]
+    .into_iter()
+    .flatten()
+    .any(|element| element.orgid.is_some())
+}
+
+fn original_elements(elements: &[IdItem]) -> Vec<IdItem> {
+    elements
+        .iter()
+        .map(|element| match element.orgid {
+            Some(id) => IdItem {
+                id,
+                name: element.orgname.clone().unwrap_or_default(),
+                orgid: None,
+                orgname: None,
+            },
+            None => element.clone(),
+        })
+        .collect()
+}
+
+fn change_kinds(before: &Lesson, after: &Lesson) -> Vec<ChangeKind> {
+    let mut kinds = Vec::new();
+    if (before.date, before.start_time, before.end_time)
+        != (after.date, after.start_time, after.end_time)
+    {
+        kinds.push(ChangeKind::Moved);
+    }
+    if element_ids(&before.rooms) != element_ids(&after.rooms) {
+        kinds.push(ChangeKind::Rooms);
+    }
+    if element_ids(&before.teachers) != element_ids(&after.teachers) {
+        kinds.push(ChangeKind::Teachers);
+    }
+    if element_ids(&before.subjects) != element_ids(&after.subjects) {
+        kinds.push(ChangeKind::Subjects);
+    }
+    match (before.code, after.code) {
+        (LessonCode::Cancelled, LessonCode::Cancelled) => {}
+        (_, LessonCode::Cancelled) => kinds.push(ChangeKind::Cancelled),
+        (LessonCode::Cancelled, _) => kinds.push(ChangeKind::Reinstated),
+        _ => {}
+    }
+    if before.lstext != after.lstext || before.subst_text != after.subst_text {
+        kinds.push(ChangeKind::Text);
+    }
+    kinds
+}
+
+fn element_ids(elements: &[IdItem]) -> Vec<isize> {
+    let mut ids: Vec<isize> = elements.iter().map(|element| element.id).collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn lesson(id: usize, start_time: u16, room: isize) -> Lesson {
+        test_util::lesson(id)
+            .starts_at(start_time)
+            .class(1, "1A")
+            .subject(2, "MA")
+            .room(room, "R")
+            .build()
+    }
+
+    #[test]
+    fn diff_of_same_timetable_is_empty() {
+        let timetable = vec![lesson(1, 800, 3), lesson(2, 900, 3)];
+        assert!(diff_timetables(&timetable, &timetable).is_empty());
+    }
+
+    #[test]
+    fn diff_detects_added_and_removed_lessons() {
+        let previous = vec![lesson(1, 800, 3), lesson(2, 900, 3)];
+        let current = vec![lesson(2, 900, 3), lesson(3, 1000, 3)];
+        let diff = diff_timetables(&previous, &current);
+
+        assert_eq!(diff.added, vec![lesson(3, 1000, 3)]);
+        assert_eq!(diff.removed, vec![lesson(1, 800, 3)]);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn diff_detects_changes_by_identity() {
+        let previous = vec![lesson(1, 800, 3)];
+        let mut changed = lesson(1, 1000, 4);
+        changed.code = LessonCode::Cancelled;
+        let diff = diff_timetables(&previous, &[changed]);
+
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(
+            diff.changed[0].kinds,
+            vec![ChangeKind::Moved, ChangeKind::Rooms, ChangeKind::Cancelled]
+        );
+    }
+
+    #[test]
+    fn planned_timetable_reverts_deviations() {
+        let regular = lesson(1, 800, 3);
+        let mut substituted = lesson(2, 900, 4);
+        substituted.rooms[0].orgid = Some(3);
+        let mut cancelled = lesson(3, 1000, 3);
+        cancelled.code = LessonCode::Cancelled;
+        let mut extra = lesson(4, 1100, 3);
+        extra.code = LessonCode::Irregular;
+
+        let actual = vec![regular, substituted, cancelled, extra];
+        let diff = diff_timetables(&planned_timetable(&actual), &actual);
+
+        assert_eq!(diff.added.iter().map(|l| l.id).collect::<Vec<_>>(), vec![4]);
+        assert!(diff.removed.is_empty());
+        let changes: Vec<_> = diff
+            .changed
+            .iter()
+            .map(|change| (change.after.id, change.kinds.clone()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (2, vec![ChangeKind::Rooms]),
+                (3, vec![ChangeKind::Cancelled])
+            ]
+        );
+    }
+
+    #[test]
+    fn irregular_substitutions_are_changes() {
+        let actual = vec![test_util::lesson(1)
+            .code("irregular")
+            .class(1, "1A")
+            .element(
+                "su",
+                serde_json::json!({ "id": 7, "name": "STU", "orgid": 2 }),
+            )
+            .room(3, "R1")
+            .teacher(5, "ABC")
+            .build()];
+        let planned = planned_timetable(&actual);
+        assert_eq!(planned[0].subjects[0].id, 2);
+
+        let diff = diff_timetables(&planned, &actual);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed[0].kinds, vec![ChangeKind::Subjects]);
+    }
+
+    #[test]
+    fn diff_detects_reinstated_lessons() {
+        let mut cancelled = lesson(1, 800, 3);
+        cancelled.code = LessonCode::Cancelled;
+        let diff = diff_timetables(&[cancelled], &[lesson(1, 800, 3)]);
+
+        assert_eq!(diff.changed[0].kinds, vec![ChangeKind::Reinstated]);
+    }
+}
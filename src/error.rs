@@ -1,44 +1,230 @@
 use std::convert::From;
 use std::fmt::{self, Display, Formatter};
 
+#[cfg(feature = "client")]
 use crate::jsonrpc;
 
 /// Represents all errors that can occur during an Untis API request.
 #[derive(Debug)]
 pub enum Error {
-    /// Error during the request itself.
+    /// Error during the request itself that doesn't fit any of the more specific variants.
+    #[cfg(feature = "client")]
     Reqwest(reqwest::Error),
 
+    /// The server could not be reached, e.g. because of a DNS, connection or TLS failure.
+    #[cfg(feature = "client")]
+    Connect(reqwest::Error),
+
+    /// The server didn't answer in time.
+    #[cfg(feature = "client")]
+    Timeout(reqwest::Error),
+
+    /// The response body could not be read or decoded.
+    #[cfg(feature = "client")]
+    Decode(reqwest::Error),
+
+    /// The response body was larger than allowed,
+    /// see [`Client::set_max_response_size()`](crate::Client::set_max_response_size).
+    #[cfg(feature = "client")]
+    ResponseTooLarge {
+        /// The largest allowed body in bytes.
+        limit: usize,
+    },
+
     /// Error while serializing/parsing data.
     Serde(serde_json::Error),
 
     /// Error with the response HTTP status code.
+    #[cfg(feature = "client")]
     Http(reqwest::StatusCode),
 
+    /// The server rate-limited the client and retrying was not possible within the [`RetryPolicy`](crate::RetryPolicy).
+    #[cfg(feature = "client")]
+    RateLimited {
+        /// How long the server asked the client to wait, if it said so.
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// The school is hosted on a different server than the one that was used, retry using the `expected` one.
+    #[cfg(feature = "client")]
+    WrongServer {
+        /// The server that the school is actually hosted on.
+        expected: String,
+    },
+
+    /// The server of a [`School`](crate::School) doesn't know it, so the record is probably outdated, e.g. because the
+    /// school was moved to another server. Search for the school again to get its current server.
+    #[cfg(feature = "client")]
+    SchoolMoved {
+        /// The login name of the school that wasn't found.
+        login_name: String,
+    },
+
     /// The RPC response contained an error.
+    #[cfg(feature = "client")]
     Rpc(jsonrpc::Error),
 
+    /// The user isn't allowed to access the requested data, e.g. because the school forbids subject timetables.
+    #[cfg(feature = "client")]
+    NoRight,
+
+    /// A school search query is shorter than the minimum length, so the server would only report too many results.
+    #[cfg(feature = "client")]
+    QueryTooShort {
+        /// The minimum number of characters, not counting surrounding whitespace.
+        min_length: usize,
+    },
+
+    /// The request was cancelled, see `cancellable()`, which requires the `cancellation` feature.
+    #[cfg(feature = "client")]
+    Cancelled,
+
+    /// A required environment variable is unset or empty.
+    #[cfg(feature = "client")]
+    MissingEnvVar(&'static str),
+
+    /// The server returned a timestamp that is out of range.
+    InvalidTimestamp(i64),
+
+    /// A date that doesn't exist in the calendar, e.g. February 30.
+    InvalidDate {
+        /// The requested year.
+        year: i32,
+        /// The requested month, starting at 1.
+        month: u32,
+        /// The requested day of the month, starting at 1.
+        day: u32,
+    },
+
+    /// Error while reading or writing a request log.
+    Io(std::io::Error),
+
     /// No results were found.
     NotFound,
 }
 
+impl Error {
+    /// Returns whether the request might succeed if it is sent again later, because the error was caused by
+    /// the network or a temporary problem of the server rather than by the request itself.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(feature = "client")]
+            Self::Connect(_) | Self::Timeout(_) | Self::RateLimited { .. } => true,
+            #[cfg(feature = "client")]
+            Self::Http(status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl Error {
+    /// Returns a hint at what probably caused the error and how to fix it, meant for end users.
+    /// It is also appended to the [`Display`] output. Use the variants themselves for handling errors in code.
+    pub fn hint(&self) -> Option<&'static str> {
+        use jsonrpc::ErrorCode;
+
+        match self {
+            Self::Rpc(err) => match ErrorCode::from_isize(err.code)? {
+                ErrorCode::InvalidCredentials => {
+                    Some("check the username and password and that the account isn't locked")
+                }
+                ErrorCode::UserBlocked => {
+                    Some("the account is locked after too many failed logins, try again later")
+                }
+                ErrorCode::NotAuthenticated => {
+                    Some("the session has probably expired, log in again")
+                }
+                ErrorCode::NoAccess => Some("the user isn't allowed to access this data"),
+                ErrorCode::InvalidSchoolName => Some("check the school's login name"),
+                ErrorCode::TooManyResults => Some("use a more specific search query"),
+                ErrorCode::MethodNotFound => Some("the server doesn't support this request"),
+                ErrorCode::InvalidParams => {
+                    Some("the server doesn't support some of the request's options")
+                }
+            },
+            Self::Connect(_) => Some("check the server name and the internet connection"),
+            Self::NoRight => Some("the school doesn't allow this for the user"),
+            Self::SchoolMoved { .. } => Some("the school may have moved, search for it again"),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         let msg = match self {
+            #[cfg(feature = "client")]
             Self::Reqwest(err) => format!("Reqwest error: {}", err),
+            #[cfg(feature = "client")]
+            Self::Connect(err) => format!("Connection error: {}", err),
+            #[cfg(feature = "client")]
+            Self::Timeout(err) => format!("Timeout: {}", err),
+            #[cfg(feature = "client")]
+            Self::Decode(err) => format!("Decode error: {}", err),
+            #[cfg(feature = "client")]
+            Self::ResponseTooLarge { limit } => {
+                format!("Response larger than the limit of {} bytes", limit)
+            }
             Self::Serde(err) => format!("Serde Error: {}", err),
+            #[cfg(feature = "client")]
             Self::Http(status) => format!("HTTP Error: {}", status),
+            #[cfg(feature = "client")]
+            Self::RateLimited {
+                retry_after: Some(delay),
+            } => format!("Rate limited, retry after {} seconds", delay.as_secs()),
+            #[cfg(feature = "client")]
+            Self::RateLimited { retry_after: None } => String::from("Rate limited"),
+            #[cfg(feature = "client")]
+            Self::WrongServer { expected } => {
+                format!("Wrong server, the school is hosted on {}", expected)
+            }
+            #[cfg(feature = "client")]
+            Self::SchoolMoved { login_name } => {
+                format!("School {} not found on its server", login_name)
+            }
+            #[cfg(feature = "client")]
             Self::Rpc(error) => format!("RPC Error: {} {}", error.code, error.message),
+            #[cfg(feature = "client")]
+            Self::NoRight => String::from("No right to access this data"),
+            #[cfg(feature = "client")]
+            Self::QueryTooShort { min_length } => {
+                format!("Query too short, use at least {} characters", min_length)
+            }
+            #[cfg(feature = "client")]
+            Self::Cancelled => String::from("Request cancelled"),
+            #[cfg(feature = "client")]
+            Self::MissingEnvVar(name) => format!("Missing environment variable: {}", name),
+            Self::InvalidTimestamp(ts) => format!("Invalid timestamp: {}", ts),
+            Self::InvalidDate { year, month, day } => {
+                format!("Invalid date: {:04}-{:02}-{:02}", year, month, day)
+            }
+            Self::Io(err) => format!("IO Error: {}", err),
             Self::NotFound => String::from("Resource not found"),
         };
 
-        formatter.write_str(&msg)
+        formatter.write_str(&msg)?;
+        #[cfg(feature = "client")]
+        if let Some(hint) = self.hint() {
+            write!(formatter, " ({})", hint)?;
+        }
+        Ok(())
     }
 }
 
+#[cfg(feature = "client")]
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
-        Self::Reqwest(err)
+        // Connecting can time out as well, so timeouts are checked first.
+        if err.is_timeout() {
+            Self::Timeout(err)
+        } else if err.is_connect() {
+            Self::Connect(err)
+        } else if err.is_decode() || err.is_body() {
+            Self::Decode(err)
+        } else {
+            Self::Reqwest(err)
+        }
     }
 }
 
@@ -47,3 +233,41 @@ impl From<serde_json::Error> for Error {
         Self::Serde(err)
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_rpc_errors_have_hints() {
+        let err = Error::Rpc(jsonrpc::Error {
+            code: -8504,
+            message: String::from("bad credentials"),
+        });
+        assert_eq!(
+            err.to_string(),
+            "RPC Error: -8504 bad credentials (check the username and password and that the account isn't locked)"
+        );
+
+        let err = Error::Rpc(jsonrpc::Error {
+            code: -1,
+            message: String::from("unknown"),
+        });
+        assert_eq!(err.hint(), None);
+        assert_eq!(err.to_string(), "RPC Error: -1 unknown");
+    }
+
+    #[test]
+    fn server_errors_and_rate_limits_are_retryable() {
+        assert!(Error::Http(reqwest::StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(Error::RateLimited { retry_after: None }.is_retryable());
+        assert!(!Error::Http(reqwest::StatusCode::NOT_FOUND).is_retryable());
+        assert!(!Error::NotFound.is_retryable());
+    }
+}
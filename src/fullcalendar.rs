@@ -0,0 +1,114 @@
+//! Conversion of lessons into the event objects of the FullCalendar web component.
+
+use crate::{Lesson, LessonCode, LessonType, ResolveContext, StatusData, Time};
+use serde_json::{json, Value};
+
+/// Converts lessons into an array of [FullCalendar](https://fullcalendar.io) event objects.
+///
+/// The title is made of the full names of the lesson's subjects, the teachers, rooms and classes are passed as
+/// `extendedProps`. Start and end are ISO 8601 times without an offset, which FullCalendar interprets in its
+/// `timeZone` setting, so set that to the school's timezone. Colors are taken from the status data, preferring the
+/// color of the lesson's code over that of its type, like the web app does. Cancelled and irregular lessons get the
+/// `untis-cancelled` and `untis-irregular` class names.
+pub fn to_fullcalendar(lessons: &[Lesson], ctx: &ResolveContext, status: &StatusData) -> Value {
+    Value::Array(
+        lessons
+            .iter()
+            .map(|lesson| event_json(lesson, ctx, status))
+            .collect(),
+    )
+}
+
+fn event_json(lesson: &Lesson, ctx: &ResolveContext, status: &StatusData) -> Value {
+    let resolved = ctx.resolve(lesson);
+    let colors = status
+        .code_color(lesson.code)
+        .or_else(|| status.lesson_type_color(lesson.lesson_type));
+    let class_names: &[&str] = match lesson.code {
+        LessonCode::Regular => &[],
+        LessonCode::Irregular => &["untis-irregular"],
+        LessonCode::Cancelled => &["untis-cancelled"],
+    };
+    let time = |time: &Time| {
+        lesson
+            .date
+            .and_time(**time)
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    };
+
+    let mut event = json!({
+        "id": lesson.id.to_string(),
+        "title": resolved.subjects.join(", "),
+        "start": time(&lesson.start_time),
+        "end": time(&lesson.end_time),
+        "classNames": class_names,
+        "extendedProps": {
+            "teachers": resolved.teachers,
+            "rooms": resolved.rooms,
+            "classes": resolved.classes,
+            "cancelled": lesson.code == LessonCode::Cancelled,
+            "exam": lesson.lesson_type == LessonType::Exam,
+            "substText": lesson.subst_text.as_deref().unwrap_or_default(),
+        },
+    });
+    if let Some(color) = colors.and_then(|colors| colors.back_color) {
+        event["backgroundColor"] = Value::String(color.to_string());
+        event["borderColor"] = Value::String(color.to_string());
+    }
+    if let Some(color) = colors.and_then(|colors| colors.fore_color) {
+        event["textColor"] = Value::String(color.to_string());
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lesson;
+
+    #[test]
+    fn lessons_become_fullcalendar_events() {
+        let lesson = lesson(1)
+            .code("cancelled")
+            .class(1, "1a")
+            .subject(2, "E")
+            .room(3, "R1")
+            .teacher(4, "SMI")
+            .build();
+        let subject = serde_json::from_value(json!({
+            "id": 2,
+            "name": "E",
+            "longName": "English",
+            "alternateName": "",
+            "active": true,
+        }))
+        .unwrap();
+        let status: StatusData = serde_json::from_value(json!({
+            "lstypes": [{ "ls": { "foreColor": "000000", "backColor": "ffffff" } }],
+            "codes": [{ "cancelled": { "foreColor": "ffffff", "backColor": "ff0000" } }],
+        }))
+        .unwrap();
+        let ctx = ResolveContext::new(&[subject], &[], &[], &[]);
+
+        let expected = json!([{
+            "id": "1",
+            "title": "English",
+            "start": "2024-05-06T08:00:00",
+            "end": "2024-05-06T08:50:00",
+            "classNames": ["untis-cancelled"],
+            "backgroundColor": "#ff0000",
+            "borderColor": "#ff0000",
+            "textColor": "#ffffff",
+            "extendedProps": {
+                "teachers": ["SMI"],
+                "rooms": ["R1"],
+                "classes": ["1a"],
+                "cancelled": true,
+                "exam": false,
+                "substText": "",
+            },
+        }]);
+        assert_eq!(to_fullcalendar(&[lesson], &ctx, &status), expected);
+    }
+}
@@ -1,5 +1,9 @@
-use crate::error;
+use crate::{error, metrics::Metrics, retry, ClientMetrics, RetryPolicy};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// Error codes contained in [Untis API errors](Error).
 /// The underlying integer can be accessed using [code.as_isize()](Self::as_isize()).
@@ -11,12 +15,30 @@ pub enum ErrorCode {
     InvalidCredentials = -8504,
     InvalidSchoolName = -8500,
     TooManyResults = -6003,
+    MethodNotFound = -32601,
+    InvalidParams = -32602,
 }
 
 impl ErrorCode {
     pub fn as_isize(&self) -> isize {
         *self as isize
     }
+
+    /// Returns the known error code with the given integer value.
+    pub fn from_isize(code: isize) -> Option<Self> {
+        [
+            Self::UserBlocked,
+            Self::NotAuthenticated,
+            Self::NoAccess,
+            Self::InvalidCredentials,
+            Self::InvalidSchoolName,
+            Self::TooManyResults,
+            Self::MethodNotFound,
+            Self::InvalidParams,
+        ]
+        .into_iter()
+        .find(|known| known.as_isize() == code)
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -60,50 +82,337 @@ pub struct Error {
     pub message: String,
 }
 
+/// A single recorded request/response pair, as written to a request log.
+///
+/// Failed exchanges are recorded as well: RPC errors as their error response, and unexpected HTTP statuses with the
+/// status and whatever body the server sent. Requests that never got a response, e.g. because of a timeout, aren't
+/// recorded.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    /// The called RPC method.
+    pub method: String,
+
+    /// The request parameters, with credentials redacted.
+    pub params: serde_json::Value,
+
+    /// The raw response body, with credentials redacted. Bodies that aren't JSON are stored as a string.
+    pub response: serde_json::Value,
+
+    /// The HTTP status of the response. Logs written before this was recorded are read as 200.
+    #[serde(default = "default_status")]
+    pub status: u16,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// The largest response body that is read by default, in bytes. Even the student lists of large schools are only a
+/// few megabytes.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// Keys whose values are never written to a request log.
+const REDACTED_KEYS: &[&str] = &["password", "sessionId"];
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_KEYS.contains(&key.as_str()) {
+                    *value = serde_json::Value::String(String::from("<redacted>"));
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(list) => list.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Reads a request log written by [`Client::record_requests()`](crate::Client::record_requests), one exchange per line.
+///
+/// The client has no pluggable transport that could answer requests from a log, so replaying the exchanges, e.g.
+/// with a local mock server, is left to the caller.
+pub fn read_log<R: BufRead>(reader: R) -> Result<Vec<Exchange>, error::Error> {
+    let mut exchanges = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            exchanges.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(exchanges)
+}
+
+/// Follows redirects on the same host, but stops at redirects to other hosts.
+/// Those happen when a school is hosted on a different server, see [`other_host()`].
+fn redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        let original_host = attempt.previous().first().and_then(|url| url.host_str());
+        if original_host != attempt.url().host_str() {
+            attempt.stop()
+        } else if attempt.previous().len() > 10 {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Returns the host that `location` redirects to, if it differs from the one of `url`.
+fn other_host(url: &reqwest::Url, location: &str) -> Option<String> {
+    let target = url.join(location).ok()?;
+    let host = target.host_str()?;
+    (Some(host) != url.host_str()).then(|| host.to_string())
+}
+
+/// Creates the id of every request, see [`Client::set_request_id_generator()`](crate::Client::set_request_id_generator).
+pub(crate) type IdGenerator = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Returns how long to wait before retrying a request that failed with `err`, or `None` if it shouldn't be retried.
+/// Only RPC errors whose code is in `retry_on_codes` are retried, as often as `policy` allows.
+fn rpc_retry_delay(
+    err: &error::Error,
+    retry_on_codes: &[isize],
+    policy: &RetryPolicy,
+    attempt: u32,
+) -> Option<std::time::Duration> {
+    match err {
+        error::Error::Rpc(err) if retry_on_codes.contains(&err.code) => policy.delay(attempt, None),
+        _ => None,
+    }
+}
+
 pub(crate) struct Client {
     http_client: reqwest::Client,
     url: String,
-    last_req_id: usize,
+    last_req_id: AtomicUsize,
+    id_generator: Option<IdGenerator>,
+    recorder: Option<Mutex<Box<dyn Write + Send>>>,
+    retry_policy: RetryPolicy,
+    retry_on_codes: Vec<isize>,
+    max_body_size: usize,
+    last_activity: Mutex<Instant>,
+    metrics: Metrics,
 }
 
 impl Client {
     pub fn new(url: &str) -> Self {
         let client = reqwest::Client::builder()
             .cookie_store(true)
+            .redirect(redirect_policy())
             .build()
             .unwrap();
         Self {
             http_client: client,
             url: url.to_string(),
-            last_req_id: 0,
+            last_req_id: AtomicUsize::new(0),
+            id_generator: None,
+            recorder: None,
+            retry_policy: RetryPolicy::default(),
+            retry_on_codes: Vec::new(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            last_activity: Mutex::new(Instant::now()),
+            metrics: Metrics::default(),
         }
     }
 
-    fn get_id(&mut self) -> String {
-        self.last_req_id += 1;
-        self.last_req_id.to_string()
+    /// Creates a client for the same url and with the same retry policy, but with its own cookies and therefore its
+    /// own session. Requests aren't recorded and the metrics start at zero.
+    pub fn fork(&self) -> Self {
+        let mut client = Self::new(&self.url);
+        client.retry_policy = self.retry_policy;
+        client.retry_on_codes = self.retry_on_codes.clone();
+        client.max_body_size = self.max_body_size;
+        client.id_generator = self.id_generator.clone();
+        client
+    }
+
+    /// Sets the policy for retrying rate-limited requests.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Sets the RPC error codes that are retried according to the retry policy.
+    pub fn set_retry_on_codes(&mut self, codes: &[isize]) {
+        self.retry_on_codes = codes.to_vec();
+    }
+
+    /// Sets the largest response body in bytes that is read before failing with
+    /// [`Error::ResponseTooLarge`](error::Error::ResponseTooLarge).
+    pub fn set_max_body_size(&mut self, bytes: usize) {
+        self.max_body_size = bytes;
+    }
+
+    /// Uses `generator` for the ids of all following requests instead of counting up from 1.
+    pub fn set_id_generator(&mut self, generator: IdGenerator) {
+        self.id_generator = Some(generator);
+    }
+
+    /// Writes every following exchange to `sink` as a JSON line.
+    pub fn set_recorder(&mut self, sink: Box<dyn Write + Send>) {
+        self.recorder = Some(Mutex::new(sink));
+    }
+
+    /// Recording is best-effort, failing to write to the sink never fails the request itself.
+    fn record<P: Serialize>(&self, method: &str, params: &P, status: u16, text: &str) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+
+        let mut exchange = Exchange {
+            method: method.to_string(),
+            params: serde_json::to_value(params).unwrap_or_default(),
+            response: serde_json::from_str(text)
+                .unwrap_or_else(|_| serde_json::Value::String(text.to_string())),
+            status,
+        };
+        redact(&mut exchange.params);
+        redact(&mut exchange.response);
+
+        let (Ok(line), Ok(mut sink)) = (serde_json::to_string(&exchange), recorder.lock()) else {
+            return;
+        };
+        let _ = writeln!(sink, "{}", line).and_then(|_| sink.flush());
+    }
+
+    /// Records a response that failed with an unexpected status. Its body is only read if requests are recorded, and
+    /// failing to read it is ignored, since the request has already failed.
+    async fn record_failure<P: Serialize>(
+        &self,
+        method: &str,
+        params: &P,
+        response: reqwest::Response,
+    ) {
+        if self.recorder.is_none() {
+            return;
+        }
+        let status = response.status().as_u16();
+        let text = self.read_body(response).await.unwrap_or_default();
+        self.record(method, params, status, &text);
+    }
+
+    /// Reads the response body, but stops as soon as it exceeds the limit, so that a huge body can't exhaust memory.
+    async fn read_body(&self, response: reqwest::Response) -> Result<String, error::Error> {
+        let length = response.content_length();
+        let chunks = futures_util::stream::unfold(response, |mut response| async move {
+            let chunk = response.chunk().await.transpose()?;
+            Some((chunk, response))
+        });
+        let body = read_limited(length, chunks, self.max_body_size).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Returns when the server last answered a request, or when this client was created if it never did.
+    pub fn last_activity(&self) -> Instant {
+        *self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns the statistics of all requests sent so far.
+    pub fn metrics(&self) -> ClientMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn get_id(&self) -> String {
+        if let Some(generator) = &self.id_generator {
+            return generator();
+        }
+        let id = self.last_req_id.fetch_add(1, Ordering::Relaxed) + 1;
+        id.to_string()
     }
 
+    /// Sends a request. Multiple requests may be in flight at the same time.
     pub async fn request<T: DeserializeOwned, P: Serialize>(
-        &mut self,
+        &self,
+        method: &'static str,
+        params: P,
+    ) -> Result<T, error::Error> {
+        self.metrics.record_request();
+        let mut attempt = 0;
+        let result = loop {
+            let result = self.send(method, &params).await;
+            let delay = match &result {
+                Err(err) => rpc_retry_delay(err, &self.retry_on_codes, &self.retry_policy, attempt),
+                Ok(_) => None,
+            };
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => break result,
+            }
+            attempt += 1;
+        };
+        if let Err(err) = &result {
+            self.metrics.record_error(err);
+        }
+        result
+    }
+
+    async fn send<T: DeserializeOwned, P: Serialize>(
+        &self,
         method: &'static str,
         params: P,
     ) -> Result<T, error::Error> {
         let req_id = &self.get_id();
         let request = Request::new(req_id, method, params);
-        let response = self
-            .http_client
-            .post(&self.url)
-            .json(&request)
-            .send()
-            .await?;
+        let mut attempt = 0;
+        let response = loop {
+            let response = self
+                .http_client
+                .post(&self.url)
+                .json(&request)
+                .send()
+                .await?;
+
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| retry::parse_retry_after(value, chrono::Utc::now()));
+
+            let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || (status == reqwest::StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some());
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|value| value.to_str().ok());
+                if let Some(expected) = location.and_then(|loc| other_host(response.url(), loc)) {
+                    return Err(error::Error::WrongServer { expected });
+                }
+            }
+
+            if !rate_limited {
+                break response;
+            }
+
+            match self.retry_policy.delay(attempt, retry_after) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => {
+                    self.record_failure(method, &request.params, response).await;
+                    return Err(error::Error::RateLimited { retry_after });
+                }
+            }
+            attempt += 1;
+        };
 
         let status = response.status();
         if !status.is_success() {
+            self.record_failure(method, &request.params, response).await;
             return Err(error::Error::Http(status));
         }
+        *self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = Instant::now();
 
-        let text = response.text().await?;
+        let text = self.read_body(response).await?;
+        self.metrics.record_bytes(text.len());
+        self.record(method, &request.params, status.as_u16(), &text);
         let response: Response<T> = serde_json::from_str(&text)?;
 
         match response {
@@ -121,3 +430,164 @@ impl Client {
         }
     }
 }
+
+/// Collects a body from its chunks, failing with [`Error::ResponseTooLarge`](error::Error::ResponseTooLarge) as
+/// soon as it is announced or turns out to be longer than `limit` bytes.
+async fn read_limited<S, C, E>(
+    length: Option<u64>,
+    chunks: S,
+    limit: usize,
+) -> Result<Vec<u8>, error::Error>
+where
+    S: futures_util::Stream<Item = Result<C, E>>,
+    C: AsRef<[u8]>,
+    error::Error: From<E>,
+{
+    let too_large = || error::Error::ResponseTooLarge { limit };
+    if length.is_some_and(|length| length > limit as u64) {
+        return Err(too_large());
+    }
+
+    let mut chunks = std::pin::pin!(chunks);
+    let mut body = Vec::new();
+    while let Some(chunk) = futures_util::StreamExt::next(&mut chunks).await {
+        let chunk = chunk?;
+        let chunk = chunk.as_ref();
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(chunk);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(chunks: &[&str]) -> impl futures_util::Stream<Item = Result<Vec<u8>, error::Error>> {
+        let chunks: Vec<_> = chunks
+            .iter()
+            .map(|chunk| Ok(chunk.as_bytes().to_vec()))
+            .collect();
+        futures_util::stream::iter(chunks)
+    }
+
+    #[tokio::test]
+    async fn read_limited_rejects_announced_oversized_body() {
+        let result = read_limited(Some(11), chunks(&["0123456789"]), 10).await;
+        assert!(matches!(
+            result,
+            Err(error::Error::ResponseTooLarge { limit: 10 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn read_limited_stops_streamed_oversized_body() {
+        let result = read_limited(None, chunks(&["012345", "6789", "x"]), 10).await;
+        assert!(matches!(
+            result,
+            Err(error::Error::ResponseTooLarge { limit: 10 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn read_limited_accepts_body_at_limit() {
+        let body = read_limited(Some(10), chunks(&["012345", "6789"]), 10)
+            .await
+            .unwrap();
+        assert_eq!(body, b"0123456789");
+    }
+
+    #[test]
+    fn redact_hides_nested_credentials() {
+        let mut value = serde_json::json!([{ "user": "name", "password": "secret" }]);
+        redact(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!([{ "user": "name", "password": "<redacted>" }])
+        );
+    }
+
+    #[test]
+    fn other_host_detects_moved_school() {
+        let url = reqwest::Url::parse("https://a.webuntis.com/WebUntis/jsonrpc.do").unwrap();
+        assert_eq!(
+            other_host(&url, "https://b.webuntis.com/WebUntis/jsonrpc.do"),
+            Some(String::from("b.webuntis.com"))
+        );
+        assert_eq!(other_host(&url, "/WebUntis/other.do"), None);
+        assert_eq!(other_host(&url, "https://a.webuntis.com/"), None);
+    }
+
+    #[test]
+    fn ids_count_up_unless_a_generator_is_set() {
+        let mut client = Client::new("https://example.webuntis.com/WebUntis/jsonrpc.do");
+        assert_eq!(client.get_id(), "1");
+        assert_eq!(client.get_id(), "2");
+
+        client.set_id_generator(Arc::new(|| String::from("custom")));
+        assert_eq!(client.get_id(), "custom");
+        assert_eq!(client.fork().get_id(), "custom");
+    }
+
+    #[test]
+    fn only_listed_rpc_codes_are_retried() {
+        let rpc = |code| {
+            error::Error::Rpc(Error {
+                code,
+                message: String::new(),
+            })
+        };
+        let policy = RetryPolicy {
+            max_retries: 2,
+            ..RetryPolicy::default()
+        };
+        let codes = [-8998];
+
+        assert!(rpc_retry_delay(&rpc(-8998), &codes, &policy, 0).is_some());
+        assert!(rpc_retry_delay(&rpc(-8998), &codes, &policy, 1).is_some());
+        assert_eq!(rpc_retry_delay(&rpc(-8998), &codes, &policy, 2), None);
+        assert_eq!(rpc_retry_delay(&rpc(-8520), &codes, &policy, 0), None);
+        assert_eq!(
+            rpc_retry_delay(&error::Error::NotFound, &codes, &policy, 0),
+            None
+        );
+        assert_eq!(rpc_retry_delay(&rpc(-8998), &[], &policy, 0), None);
+    }
+
+    #[test]
+    fn read_log_skips_empty_lines() {
+        let log = "{\"method\":\"getRooms\",\"params\":null,\"response\":{}}\n\n";
+        let exchanges = read_log(log.as_bytes()).unwrap();
+        assert_eq!(exchanges.len(), 1);
+        assert_eq!(exchanges[0].method, "getRooms");
+        assert_eq!(exchanges[0].status, 200);
+    }
+
+    #[test]
+    fn failed_exchanges_are_recorded_with_their_status() {
+        #[derive(Clone, Default)]
+        struct Sink(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let sink = Sink::default();
+        let mut client = Client::new("https://example.webuntis.com/WebUntis/jsonrpc.do");
+        client.set_recorder(Box::new(sink.clone()));
+        client.record("getRooms", &(), 502, "<html>Bad Gateway</html>");
+
+        let log = sink.0.lock().unwrap().clone();
+        let exchanges = read_log(log.as_slice()).unwrap();
+        assert_eq!(exchanges[0].status, 502);
+        assert_eq!(exchanges[0].response, "<html>Bad Gateway</html>");
+    }
+}
@@ -28,17 +28,78 @@
 //! }
 //! ```
 //! For more examples, see the `examples/` directory.
+//!
+//! ## Features
+//! - `client` (default): The HTTP client for accessing the API. Without it, only the resource and date types and their
+//!   serde implementations are available, e.g. for deserializing already fetched data in a WASM frontend.
+//! - `cancellation`: `untis::cancellable()` for aborting requests with a `tokio_util` `CancellationToken`.
+//! - `fullcalendar`: Conversion of lessons into FullCalendar events, see `untis::to_fullcalendar()`.
+//! - `proptest`: `proptest::arbitrary::Arbitrary` implementations for `Date` and `Time`, which only generate values
+//!   that Untis can represent, for property-testing code that works with timetables.
+//! - `render`: Rendering of timetables as HTML tables, see `untis::render::to_html()`.
+//! - `webuntis-json`: Conversion of lessons into the JSON shape that the official WebUntis web app consumes,
+//!   see `Lesson::to_webuntis_json()`.
 
+#[cfg(feature = "client")]
+mod cache;
+#[cfg(feature = "cancellation")]
+mod cancel;
+#[cfg(feature = "client")]
 mod client;
+mod color;
 mod datetime;
+mod diff;
 mod error;
+#[cfg(feature = "fullcalendar")]
+mod fullcalendar;
+#[cfg(feature = "client")]
+mod metrics;
+#[cfg(feature = "client")]
 mod params;
+mod resolve;
 mod resources;
+#[cfg(feature = "client")]
+mod retry;
+#[cfg(feature = "client")]
+mod shared;
+#[cfg(test)]
+mod test_util;
+mod timetables;
+#[cfg(feature = "webuntis-json")]
+mod webuntis_json;
 
+#[cfg(feature = "client")]
 pub mod jsonrpc;
+#[cfg(feature = "client")]
+pub mod quick;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "client")]
 pub mod schools;
 
+#[cfg(feature = "cancellation")]
+pub use cancel::cancellable;
+#[cfg(feature = "client")]
 pub use client::Client;
+pub use color::Color;
 pub use datetime::*;
+pub use diff::*;
 pub use error::Error;
+#[cfg(feature = "fullcalendar")]
+pub use fullcalendar::to_fullcalendar;
+#[cfg(feature = "client")]
+pub use metrics::ClientMetrics;
+#[cfg(feature = "client")]
+pub use params::TimetableOptions;
+pub use resolve::{ResolveContext, ResolvedLesson};
 pub use resources::*;
+#[cfg(feature = "client")]
+pub use retry::RetryPolicy;
+#[cfg(feature = "client")]
+pub use shared::SharedClient;
+pub use timetables::{
+    group_by_student_group, hours_by_subject, hours_by_teacher, sort_lessons, summarize_week,
+    LessonGroup, PartialTimetable, SubjectSummary, Timetables, Workload,
+};
+#[cfg(feature = "webuntis-json")]
+pub use webuntis_json::timetable_to_webuntis_json;
@@ -0,0 +1,111 @@
+use crate::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Cumulative request statistics of a client, returned by [`Client::metrics()`](crate::Client::metrics).
+///
+/// Every call counts as one request, no matter how often it was retried after being rate-limited.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct ClientMetrics {
+    /// Number of requests sent.
+    pub requests: u64,
+
+    /// Requests that failed because the server couldn't be reached or didn't answer in time.
+    pub network_errors: u64,
+
+    /// Requests that failed with an unexpected HTTP status or a redirect to another server.
+    pub http_errors: u64,
+
+    /// Requests that were still rate-limited after all retries.
+    pub rate_limited: u64,
+
+    /// Responses that couldn't be decoded.
+    pub decode_errors: u64,
+
+    /// Requests that Untis answered with an error, e.g. because the user isn't allowed to access the data.
+    pub rpc_errors: u64,
+
+    /// Total size of all received response bodies, in bytes.
+    pub bytes_received: u64,
+}
+
+impl ClientMetrics {
+    /// Returns the number of failed requests.
+    pub fn errors(&self) -> u64 {
+        self.network_errors
+            + self.http_errors
+            + self.rate_limited
+            + self.decode_errors
+            + self.rpc_errors
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    requests: AtomicU64,
+    network_errors: AtomicU64,
+    http_errors: AtomicU64,
+    rate_limited: AtomicU64,
+    decode_errors: AtomicU64,
+    rpc_errors: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_bytes(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, err: &Error) {
+        let counter = match err {
+            Error::Rpc(_) => &self.rpc_errors,
+            Error::RateLimited { .. } => &self.rate_limited,
+            Error::Http(_) | Error::WrongServer { .. } => &self.http_errors,
+            Error::Decode(_)
+            | Error::Serde(_)
+            | Error::InvalidTimestamp(_)
+            | Error::ResponseTooLarge { .. } => &self.decode_errors,
+            _ => &self.network_errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ClientMetrics {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ClientMetrics {
+            requests: load(&self.requests),
+            network_errors: load(&self.network_errors),
+            http_errors: load(&self.http_errors),
+            rate_limited: load(&self.rate_limited),
+            decode_errors: load(&self.decode_errors),
+            rpc_errors: load(&self.rpc_errors),
+            bytes_received: load(&self.bytes_received),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_counted_by_kind() {
+        let metrics = Metrics::default();
+        metrics.record_request();
+        metrics.record_request();
+        metrics.record_bytes(42);
+        metrics.record_error(&Error::Http(reqwest::StatusCode::BAD_GATEWAY));
+        metrics.record_error(&Error::RateLimited { retry_after: None });
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests, 2);
+        assert_eq!(snapshot.bytes_received, 42);
+        assert_eq!(snapshot.http_errors, 1);
+        assert_eq!(snapshot.rate_limited, 1);
+        assert_eq!(snapshot.errors(), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{datetime::Date, ElementType};
+use crate::{datetime::Date, ElementKey, ElementRef, ElementType};
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -39,11 +39,170 @@ pub struct TimetableParamsOpts<'a> {
     pub room_fields: &'a [&'a str],
     pub subject_fields: &'a [&'a str],
     pub teacher_fields: &'a [&'a str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department_id: Option<&'a usize>,
+}
+
+/// Controls how timetables are requested and which optional fields are requested for every lesson.
+///
+/// Everything is requested by default. Turning fields off keeps the server from sending them at all, e.g. student
+/// groups or booking info in deployments that don't want to handle that data. Lessons fetched without a field
+/// have it set to its empty value. If a school rejects the requested fields, the request is repeated once
+/// [`without_optional_fields()`](Self::without_optional_fields).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TimetableOptions {
+    /// Request booking info of the lessons.
+    pub show_booking: bool,
+
+    /// Request the info text of the lessons.
+    pub show_info: bool,
+
+    /// Request the substitution text, see [`Lesson::subst_text`](crate::Lesson::subst_text).
+    pub show_subst_text: bool,
+
+    /// Request the lesson text, see [`Lesson::lstext`](crate::Lesson::lstext).
+    pub show_ls_text: bool,
+
+    /// Request the student group, see [`Lesson::student_group`](crate::Lesson::student_group).
+    pub show_student_group: bool,
+
+    /// The longest range in days that is requested at once, 31 by default. Untis rejects overly long ranges, so
+    /// longer ones are split into consecutive requests whose lessons are concatenated. 0 disables splitting.
+    pub max_span_days: u32,
+
+    /// Request whole weeks and drop the lessons outside of the requested range afterwards, for servers that only
+    /// answer week-granular requests correctly. Off by default.
+    ///
+    /// Weeks are six days long and start on the client's [week start](crate::Client::set_week_start), e.g. monday to
+    /// saturday by default. A range ending on the seventh day of a week is extended to that day.
+    pub align_to_weeks: bool,
+
+    /// Only request the lessons of the [department](crate::Department) with this id, e.g. the math lessons of a
+    /// teacher who works in several departments. Not sent if `None`, which is the default.
+    ///
+    /// Only some deployments support this filter, others ignore it or reject the request. Unlike the optional
+    /// fields, it is kept when a rejected request is repeated, since dropping it would return unfiltered lessons.
+    pub department_id: Option<usize>,
+}
+
+impl TimetableOptions {
+    /// Returns these options with every optional field turned off. Timetables are requested like this if the server
+    /// rejects the fields that were asked for.
+    pub fn without_optional_fields(&self) -> Self {
+        Self {
+            show_booking: false,
+            show_info: false,
+            show_subst_text: false,
+            show_ls_text: false,
+            show_student_group: false,
+            ..*self
+        }
+    }
+}
+
+impl Default for TimetableOptions {
+    fn default() -> Self {
+        Self {
+            show_booking: true,
+            show_info: true,
+            show_subst_text: true,
+            show_ls_text: true,
+            show_student_group: true,
+            max_span_days: 31,
+            align_to_weeks: false,
+            department_id: None,
+        }
+    }
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TimetableParamsElem<'a> {
-    pub id: &'a usize,
+    pub id: TimetableParamsId<'a>,
     #[serde(rename = "type")]
     pub ty: &'a ElementType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum TimetableParamsId<'a> {
+    Id(&'a usize),
+    Name(&'a str),
+}
+
+impl<'a> From<&'a ElementRef> for TimetableParamsElem<'a> {
+    fn from(element: &'a ElementRef) -> Self {
+        let (id, key_type) = match &element.key {
+            ElementKey::Id(id) => (TimetableParamsId::Id(id), None),
+            ElementKey::Name(name) => (TimetableParamsId::Name(name), Some("name")),
+            ElementKey::ExternalKey(key) => (TimetableParamsId::Name(key), Some("externalkey")),
+        };
+        Self {
+            id,
+            ty: &element.ty,
+            key_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn element_by_id_omits_key_type() {
+        let element = ElementRef::new(42, ElementType::Class);
+        let json = serde_json::to_value(TimetableParamsElem::from(&element)).unwrap();
+        assert_eq!(json, serde_json::json!({ "id": 42, "type": 1 }));
+    }
+
+    #[test]
+    fn element_by_name_sets_key_type() {
+        let element = ElementRef::by_name("10b", ElementType::Class);
+        let json = serde_json::to_value(TimetableParamsElem::from(&element)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": "10b", "type": 1, "keyType": "name" })
+        );
+    }
+
+    #[test]
+    fn department_id_is_only_sent_if_set() {
+        let element = ElementRef::new(42, ElementType::Teacher);
+        let element = TimetableParamsElem::from(&element);
+        let date = Date::today();
+        let opts = |department_id| TimetableParamsOpts {
+            element: &element,
+            start_date: &date,
+            end_date: &date,
+            show_booking: &true,
+            show_info: &true,
+            show_subst_text: &true,
+            show_ls_text: &true,
+            show_ls_number: &true,
+            show_student_group: &true,
+            class_fields: &[],
+            room_fields: &[],
+            subject_fields: &[],
+            teacher_fields: &[],
+            department_id,
+        };
+
+        let json = serde_json::to_value(opts(None)).unwrap();
+        assert!(json.get("departmentId").is_none());
+        let json = serde_json::to_value(opts(Some(&7))).unwrap();
+        assert_eq!(json["departmentId"], 7);
+    }
+
+    #[test]
+    fn element_by_external_key_sets_key_type() {
+        let element = ElementRef::by_external_key("T-0001", ElementType::Teacher);
+        let json = serde_json::to_value(TimetableParamsElem::from(&element)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": "T-0001", "type": 2, "keyType": "externalkey" })
+        );
+    }
 }
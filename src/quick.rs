@@ -0,0 +1,31 @@
+//! One-shot helpers for scripts, which log in, fetch something and log out again.
+//! Use a [`Client`] for anything that makes more than one request.
+
+use crate::{Client, Error, Lesson};
+
+/// Logs in, retrieves the user's own timetable for the current week, sorted chronologically, and logs out again.
+///
+/// The session is logged out even if fetching the timetable fails, in which case that error is returned
+/// instead of a possible logout error.
+///
+/// # Example
+/// ```no_run
+/// # async fn run() -> Result<(), untis::Error> {
+/// let lessons = untis::quick::own_week("server.webuntis.com", "school", "username", "password").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn own_week(
+    server: &str,
+    school: &str,
+    username: &str,
+    password: &str,
+) -> Result<Vec<Lesson>, Error> {
+    let mut client = Client::login(server, school, username, password).await?;
+    let lessons = client.own_timetable_current_week_sorted().await;
+    let logout = client.logout().await;
+
+    let lessons = lessons?;
+    logout?;
+    Ok(lessons)
+}
@@ -0,0 +1,172 @@
+//! Rendering of timetables as HTML, e.g. for embedding them in a web page or an email.
+
+use crate::{Lesson, LessonCode, Subject, TimegridDay};
+use std::fmt::Write;
+
+const STYLE: &str = "\
+.untis-timetable { border-collapse: collapse; font-family: sans-serif; font-size: 0.9em; }
+.untis-timetable th, .untis-timetable td { border: 1px solid #ccc; padding: 4px; vertical-align: top; }
+.untis-timetable .lesson { padding: 2px 4px; margin-bottom: 2px; border-radius: 3px; }
+.untis-timetable .cancelled { text-decoration: line-through; opacity: 0.6; }
+.untis-timetable .irregular { font-weight: bold; }
+";
+
+/// Renders lessons as an HTML table with one column per weekday of the timegrid and one row per period.
+///
+/// Equivalent to [`to_html_with_subjects()`] without any subjects, so all lessons use the default colors.
+pub fn to_html(lessons: &[Lesson], timegrid: &[TimegridDay]) -> String {
+    to_html_with_subjects(lessons, timegrid, &[])
+}
+
+/// Renders lessons as an HTML table like [`to_html()`], coloring every lesson with the colors of its first subject.
+///
+/// The lessons should be a single week, e.g. from `Client::own_timetable_current_week()`, since lessons are placed by
+/// weekday. Every lesson goes into the period it starts in, see [`Lesson::period_index()`], lessons outside of all
+/// periods are left out. Cancelled and irregular lessons get the `cancelled` and `irregular` CSS classes. The output
+/// includes a small `<style>` element and can be embedded as is.
+pub fn to_html_with_subjects(
+    lessons: &[Lesson],
+    timegrid: &[TimegridDay],
+    subjects: &[Subject],
+) -> String {
+    let mut days: Vec<&TimegridDay> = timegrid
+        .iter()
+        .filter(|day| day.weekday().is_some())
+        .collect();
+    // Untis counts from sunday, but weeks are displayed starting on monday.
+    days.sort_by_key(|day| day.weekday().map(|weekday| weekday.num_days_from_monday()));
+    let rows = days.iter().copied().max_by_key(|day| day.time_units.len());
+
+    let mut html = String::new();
+    let _ = writeln!(html, "<style>\n{}</style>", STYLE);
+    html.push_str("<table class=\"untis-timetable\">\n<tr><th></th>");
+    for day in &days {
+        let weekday = day.weekday().map(|weekday| weekday.to_string());
+        let _ = write!(html, "<th>{}</th>", weekday.unwrap_or_default());
+    }
+    html.push_str("</tr>\n");
+
+    for (index, unit) in rows
+        .map_or(&[][..], |day| &day.time_units)
+        .iter()
+        .enumerate()
+    {
+        let _ = write!(
+            html,
+            "<tr><th>{}<br>{}-{}</th>",
+            escape(&unit.name),
+            unit.start_time.format("%H:%M"),
+            unit.end_time.format("%H:%M"),
+        );
+        for day in &days {
+            html.push_str("<td>");
+            let in_cell = lessons.iter().filter(|lesson| {
+                day.weekday() == Some(chrono::Datelike::weekday(&*lesson.date))
+                    && lesson.period_index(timegrid) == Some(index as u32)
+            });
+            for lesson in in_cell {
+                lesson_html(&mut html, lesson, subjects);
+            }
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn lesson_html(html: &mut String, lesson: &Lesson, subjects: &[Subject]) {
+    let class = match lesson.code {
+        LessonCode::Regular => "lesson",
+        LessonCode::Irregular => "lesson irregular",
+        LessonCode::Cancelled => "lesson cancelled",
+    };
+    let subject = lesson.subjects.first().and_then(|item| {
+        subjects
+            .iter()
+            .find(|subject| subject.id as isize == item.id)
+    });
+    let mut style = String::new();
+    if let Some(color) = subject.and_then(|subject| subject.back_color) {
+        let _ = write!(style, "background-color: {};", color);
+    }
+    if let Some(color) = subject.and_then(|subject| subject.fore_color) {
+        let _ = write!(style, "color: {};", color);
+    }
+
+    let _ = write!(html, "<div class=\"{}\"", class);
+    if !style.is_empty() {
+        let _ = write!(html, " style=\"{}\"", style);
+    }
+    let _ = write!(
+        html,
+        "><b>{}</b> {} {}</div>",
+        escape(lesson.primary_subject().unwrap_or_default()),
+        escape(lesson.primary_teacher().unwrap_or_default()),
+        escape(lesson.primary_room().unwrap_or_default()),
+    );
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lesson;
+
+    #[test]
+    fn lessons_are_placed_in_their_period_with_subject_colors() {
+        let timegrid: Vec<TimegridDay> = serde_json::from_value(serde_json::json!([
+            { "day": 3, "timeUnits": [{ "name": "1", "startTime": 800, "endTime": 850 }] },
+            {
+                "day": 2,
+                "timeUnits": [
+                    { "name": "1", "startTime": 800, "endTime": 850 },
+                    { "name": "2", "startTime": 900, "endTime": 950 },
+                ],
+            },
+        ]))
+        .unwrap();
+        let lesson = lesson(1)
+            .starts_at(900)
+            .code("cancelled")
+            .subject(2, "M<A")
+            .room(3, "R1")
+            .teacher(4, "ABC")
+            .build();
+        let subject: Subject = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "name": "M<A",
+            "longName": "Math",
+            "alternateName": "",
+            "active": true,
+            "backColor": "ff0000",
+        }))
+        .unwrap();
+
+        let html = to_html_with_subjects(&[lesson], &timegrid, &[subject]);
+        let monday = html.find("<th>Mon</th>").unwrap();
+        assert!(monday < html.find("<th>Tue</th>").unwrap());
+        let second_period = html
+            .lines()
+            .find(|line| line.contains("09:00-09:50"))
+            .unwrap();
+        assert!(second_period.contains(
+            "<td><div class=\"lesson cancelled\" style=\"background-color: #ff0000;\"><b>M&lt;A</b> ABC R1</div></td><td></td>"
+        ));
+        assert!(!to_html(&[], &timegrid).contains("class=\"lesson"));
+    }
+}
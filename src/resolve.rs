@@ -0,0 +1,156 @@
+use crate::resources::{Class, IdItem, Lesson, Room, Subject, Teacher};
+use std::collections::HashMap;
+
+/// Full names of a school's subjects, teachers, rooms and classes, used to resolve the short names in lessons.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ResolveContext {
+    subjects: HashMap<isize, String>,
+    teachers: HashMap<isize, String>,
+    rooms: HashMap<isize, String>,
+    classes: HashMap<isize, String>,
+}
+
+impl ResolveContext {
+    /// Creates a context from master data lists. Any of them may be empty, e.g. if the user isn't allowed to list
+    /// teachers, in which case the short names from the lessons are used.
+    pub fn new(
+        subjects: &[Subject],
+        teachers: &[Teacher],
+        rooms: &[Room],
+        classes: &[Class],
+    ) -> Self {
+        Self {
+            subjects: names(subjects, |subject| (subject.id, subject.long_name.clone())),
+            teachers: names(teachers, |teacher| {
+                let name = format!("{} {}", teacher.first_name, teacher.last_name);
+                (teacher.id, name.trim().to_string())
+            }),
+            rooms: names(rooms, |room| (room.id, room.long_name.clone())),
+            classes: names(classes, |class| (class.id, class.long_name.clone())),
+        }
+    }
+
+    /// Resolves the names of every element of a lesson.
+    pub fn resolve(&self, lesson: &Lesson) -> ResolvedLesson {
+        ResolvedLesson {
+            subjects: resolve_all(&lesson.subjects, &self.subjects),
+            teachers: resolve_all(&lesson.teachers, &self.teachers),
+            rooms: resolve_all(&lesson.rooms, &self.rooms),
+            classes: resolve_all(&lesson.classes, &self.classes),
+            lesson: lesson.clone(),
+        }
+    }
+}
+
+fn names<T>(items: &[T], entry: impl Fn(&T) -> (usize, String)) -> HashMap<isize, String> {
+    items
+        .iter()
+        .map(entry)
+        .filter(|(_, name)| !name.is_empty())
+        .map(|(id, name)| (id as isize, name))
+        .collect()
+}
+
+fn resolve_all(items: &[IdItem], names: &HashMap<isize, String>) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| names.get(&item.id).unwrap_or(&item.name).clone())
+        .collect()
+}
+
+/// A lesson together with the full names of its elements, as returned by
+/// [`Client::timetable_with_resolved_names()`](crate::Client::timetable_with_resolved_names).
+///
+/// Every list has the same order as the corresponding list of the lesson. Elements whose full name is unknown keep
+/// their short name.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ResolvedLesson {
+    /// The lesson itself.
+    pub lesson: Lesson,
+
+    /// Full names of the lesson's subjects.
+    pub subjects: Vec<String>,
+
+    /// Full names of the lesson's teachers, formatted as `first_name last_name`.
+    pub teachers: Vec<String>,
+
+    /// Full names of the lesson's rooms.
+    pub rooms: Vec<String>,
+
+    /// Full names of the lesson's classes.
+    pub classes: Vec<String>,
+}
+
+impl ResolvedLesson {
+    /// Returns whether every word of `query` is part of the short or full name of one of the lesson's subjects,
+    /// teachers, rooms or classes, ignoring case.
+    pub fn matches_text(&self, query: &str) -> bool {
+        let lesson = &self.lesson;
+        let text = [
+            &lesson.subjects,
+            &lesson.teachers,
+            &lesson.rooms,
+            &lesson.classes,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|item| &item.name)
+        .chain(
+            [&self.subjects, &self.teachers, &self.rooms, &self.classes]
+                .into_iter()
+                .flatten(),
+        )
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+        query
+            .split_whitespace()
+            .all(|word| text.contains(&word.to_lowercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lesson;
+
+    #[test]
+    fn resolves_known_ids_and_keeps_short_names_otherwise() {
+        let lesson = lesson(1)
+            .class(1, "1a")
+            .subject(2, "E")
+            .room(3, "R1")
+            .teacher(4, "SMI")
+            .teacher(5, "MIL")
+            .build();
+        let subject: Subject = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "name": "E",
+            "longName": "English",
+            "alternateName": "",
+            "active": true,
+        }))
+        .unwrap();
+        let teacher: Teacher = serde_json::from_value(serde_json::json!({
+            "id": 4,
+            "name": "SMI",
+            "foreName": "John",
+            "longName": "Smith",
+            "title": "",
+            "active": true,
+            "dids": [],
+        }))
+        .unwrap();
+
+        let resolved = ResolveContext::new(&[subject], &[teacher], &[], &[]).resolve(&lesson);
+        assert_eq!(resolved.subjects, vec!["English"]);
+        assert_eq!(resolved.teachers, vec!["John Smith", "MIL"]);
+        assert_eq!(resolved.rooms, vec!["R1"]);
+        assert_eq!(resolved.classes, vec!["1a"]);
+
+        assert!(resolved.matches_text("english smith"));
+        assert!(resolved.matches_text("r1 SMI"));
+        assert!(!resolved.matches_text("english miller"));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::color::{self, Color};
 use crate::datetime::{Date, Time};
+use chrono::Datelike;
 use serde::{Deserialize, Serialize};
 use serde_repr::*;
 use std::collections::HashMap;
@@ -20,6 +22,63 @@ impl ElementType {
     }
 }
 
+/// A reference to an element whose timetable can be fetched.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub struct ElementRef {
+    /// What identifies the element.
+    pub key: ElementKey,
+
+    /// The element's type.
+    #[serde(rename = "type")]
+    pub ty: ElementType,
+}
+
+/// The different ways of identifying an element.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum ElementKey {
+    /// The element's internal id.
+    Id(usize),
+
+    /// The element's short name, e.g. `10b` for a class.
+    Name(String),
+
+    /// The element's key in external systems, see e.g. [`Teacher::extern_id`].
+    ExternalKey(String),
+}
+
+impl ElementRef {
+    /// References an element by its internal id.
+    pub fn new(id: usize, ty: ElementType) -> Self {
+        Self {
+            key: ElementKey::Id(id),
+            ty,
+        }
+    }
+
+    /// References an element by its short name, e.g. `10b` or `MUE`.
+    ///
+    /// Internal ids may change between schoolyears, while short names usually stay the same, which makes these
+    /// references better suited for being persisted. On the other hand, short names can be renamed at any time and
+    /// are only unique within their element type, so a stale name fails the request instead of silently referencing
+    /// a different element. Prefer [`ElementRef::new()`] for references that only live during a session.
+    pub fn by_name(name: &str, ty: ElementType) -> Self {
+        Self {
+            key: ElementKey::Name(name.to_string()),
+            ty,
+        }
+    }
+
+    /// References an element by its key in an external system, e.g. a student information system that imports its
+    /// data into Untis. Only works for elements that the school has assigned external keys to.
+    pub fn by_external_key(key: &str, ty: ElementType) -> Self {
+        Self {
+            key: ElementKey::ExternalKey(key.to_string()),
+            ty,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
 pub(crate) struct SchoolSearchResult {
     pub size: usize,
@@ -35,9 +94,18 @@ pub struct School {
 
     pub use_mobile_service_url_android: bool,
 
-    /// The school's address.
+    /// The school's address, usually including the postal code and city. Empty if the server doesn't send one.
+    #[serde(default)]
     pub address: String,
 
+    /// The school's city, if the server sends it separately from the address.
+    #[serde(default)]
+    pub city: Option<String>,
+
+    /// The region or state that the school is in, if the server sends it.
+    #[serde(default)]
+    pub region: Option<String>,
+
     /// The school's full name.
     pub display_name: String,
 
@@ -54,6 +122,35 @@ pub struct School {
     pub server_url: String,
 
     pub mobile_service_url: Option<String>,
+
+    /// The id of the school's tenant on the Untis cloud platform, if the server sends it.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+
+    /// The IANA name of the school's time zone, e.g. `Europe/Vienna`, if the server sends it.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl School {
+    /// Returns the servers that this school can be reached on, starting with [`server`](Self::server).
+    /// The host of [`server_url`](Self::server_url) is added if it differs, which happens while schools migrate
+    /// between servers.
+    pub fn candidate_servers(&self) -> Vec<String> {
+        let mut servers = vec![self.server.clone()];
+        if let Some(host) = url_host(&self.server_url) {
+            if !servers.iter().any(|server| server == host) {
+                servers.push(host.to_string());
+            }
+        }
+        servers
+    }
+}
+
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#', ':']).next()?;
+    (!host.is_empty()).then_some(host)
 }
 
 /// A Untis session.
@@ -63,9 +160,13 @@ pub struct Session {
     /// The session's id.
     pub session_id: String,
 
-    /// Id of the user's class.
-    #[serde(rename = "klasseId")]
-    pub class_id: usize,
+    /// Id of the user's class, `None` for users that aren't in a class, like teachers.
+    #[serde(
+        rename = "klasseId",
+        default,
+        deserialize_with = "deserialize_optional_id"
+    )]
+    pub class_id: Option<usize>,
 
     /// The user's id.
     pub person_id: usize,
@@ -74,6 +175,29 @@ pub struct Session {
     pub person_type: ElementType,
 }
 
+impl Session {
+    /// Returns the element whose timetable is the user's own: the teacher for teachers, and the student for students,
+    /// which also contains the lessons of their class.
+    ///
+    /// Returns `None` for accounts that aren't linked to a teacher or student, e.g. administrative accounts, which Untis
+    /// sends with a person id of 0. Their timetable would be that of an unrelated element.
+    pub fn own_element(&self) -> Option<ElementRef> {
+        match self.person_type {
+            ElementType::Teacher | ElementType::Student if self.person_id != 0 => {
+                Some(ElementRef::new(self.person_id, self.person_type.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Deserializes an id that Untis sends as 0 when it is absent.
+fn deserialize_optional_id<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    Ok(Option::<usize>::deserialize(deserializer)?.filter(|id| *id != 0))
+}
+
 /// A set of colors that can be used to display a timetable.
 #[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct StatusData {
@@ -84,15 +208,37 @@ pub struct StatusData {
     pub codes: Vec<HashMap<String, StatusDataItem>>,
 }
 
+impl StatusData {
+    /// Returns the colors for displaying lessons of the given type.
+    pub fn lesson_type_color(&self, lesson_type: LessonType) -> Option<&StatusDataItem> {
+        find_status_item(&self.lstypes, lesson_type.untis_key())
+    }
+
+    /// Returns the colors for displaying lessons with the given code.
+    /// Regular lessons don't have a special color, so this returns `None` for [`LessonCode::Regular`].
+    pub fn code_color(&self, code: LessonCode) -> Option<&StatusDataItem> {
+        find_status_item(&self.codes, code.untis_key()?)
+    }
+}
+
+fn find_status_item<'a>(
+    items: &'a [HashMap<String, StatusDataItem>],
+    key: &str,
+) -> Option<&'a StatusDataItem> {
+    items.iter().find_map(|item| item.get(key))
+}
+
 /// Color information to display a specific lesson.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StatusDataItem {
-    /// Foreground color, formatted as `RRGGBB`.
-    pub fore_color: String,
+    /// Foreground color, `None` if the server sent none or an invalid one.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub fore_color: Option<Color>,
 
-    /// Background color, formatted as `RRGGBB`.
-    pub back_color: String,
+    /// Background color, `None` if the server sent none or an invalid one.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub back_color: Option<Color>,
 }
 
 /// A schoolyear.
@@ -112,6 +258,26 @@ pub struct Schoolyear {
     pub end_date: Date,
 }
 
+impl Schoolyear {
+    /// Returns whether `today` is part of this schoolyear. Both the start and end date are part of it.
+    pub fn is_current(&self, today: &Date) -> bool {
+        self.start_date <= *today && *today <= self.end_date
+    }
+
+    /// Trims the range from `start` to `end`, both inclusive, to the part that lies within this schoolyear.
+    /// Returns `None` if the range doesn't overlap with the schoolyear at all.
+    pub fn clamp_range(&self, start: Date, end: Date) -> Option<(Date, Date)> {
+        clamp(start, end, self.start_date, self.end_date)
+    }
+}
+
+/// Trims the range from `start` to `end` to the part that lies within `first` to `last`, all inclusive.
+fn clamp(start: Date, end: Date, first: Date, last: Date) -> Option<(Date, Date)> {
+    let start = start.max(first);
+    let end = end.min(last);
+    (start <= end).then_some((start, end))
+}
+
 /// A school holiday.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -132,6 +298,50 @@ pub struct Holiday {
     pub end_date: Date,
 }
 
+impl Holiday {
+    /// Trims the range from `start` to `end`, both inclusive, to the days of it that are part of this holiday.
+    /// Returns `None` if the range doesn't overlap with the holiday at all.
+    pub fn clamp_range(&self, start: Date, end: Date) -> Option<(Date, Date)> {
+        clamp(start, end, self.start_date, self.end_date)
+    }
+}
+
+/// The periods of a single weekday, as returned by [`Client::timegrid()`](crate::Client::timegrid).
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimegridDay {
+    /// The weekday, counted from 1 for Sunday to 7 for Saturday like Untis does. See [`weekday()`](Self::weekday).
+    pub day: u8,
+
+    /// The day's periods, ordered by their start time.
+    pub time_units: Vec<TimeUnit>,
+}
+
+impl TimegridDay {
+    /// Returns the weekday, or `None` if the server sent an invalid day.
+    pub fn weekday(&self) -> Option<chrono::Weekday> {
+        use chrono::Weekday::*;
+        [Sun, Mon, Tue, Wed, Thu, Fri, Sat]
+            .get(usize::from(self.day).checked_sub(1)?)
+            .copied()
+    }
+}
+
+/// A single period of the timegrid.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeUnit {
+    /// The period's label, usually its number like `1`.
+    #[serde(default)]
+    pub name: String,
+
+    /// The period's start time.
+    pub start_time: Time,
+
+    /// The period's end time.
+    pub end_time: Time,
+}
+
 /// Represents a room for school lessons.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -148,11 +358,13 @@ pub struct Room {
     /// Whether the room is generally available or not used in the system.
     pub active: bool,
 
-    /// Foreground color for displaying the room, formatted as `RRGGBB`.
-    pub fore_color: Option<String>,
+    /// Foreground color for displaying the room.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub fore_color: Option<Color>,
 
-    /// Background color for displaying the room, formatted as `RRGGBB`.
-    pub back_color: Option<String>,
+    /// Background color for displaying the room.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub back_color: Option<Color>,
 
     /// The building that this room is located in. May be an empty string if you school hasn't configured any.
     pub building: String,
@@ -176,11 +388,13 @@ pub struct Class {
     /// Whether the class is generally available or not used in the system.
     pub active: bool,
 
-    /// Foreground color for displaying the class, formatted as `RRGGBB`.
-    pub fore_color: Option<String>,
+    /// Foreground color for displaying the class.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub fore_color: Option<Color>,
 
-    /// Background color for displaying the class, formatted as `RRGGBB`.
-    pub back_color: Option<String>,
+    /// Background color for displaying the class.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub back_color: Option<Color>,
 
     pub did: Option<usize>,
 
@@ -206,16 +420,23 @@ pub struct Subject {
     /// The subject's full name.
     pub long_name: String,
 
+    /// The subject's alternative name, which some schools use for a curriculum or reporting code. Empty if unused.
     pub alternate_name: String,
 
+    /// The subject's key in external systems, e.g. a national curriculum catalog, if the school has configured one.
+    #[serde(rename = "externKey", default)]
+    pub extern_id: Option<String>,
+
     /// Whether the subject is generally available or not used in the system.
     pub active: bool,
 
-    /// Foreground color for displaying the subject, formatted as `RRGGBB`.
-    pub fore_color: Option<String>,
+    /// Foreground color for displaying the subject.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub fore_color: Option<Color>,
 
-    /// Background color for displaying the subject, formatted as `RRGGBB`.
-    pub back_color: Option<String>,
+    /// Background color for displaying the subject.
+    #[serde(default, deserialize_with = "color::deserialize_optional")]
+    pub back_color: Option<Color>,
 }
 
 /// Represents a teacher.
@@ -243,6 +464,18 @@ pub struct Teacher {
     pub active: bool,
 
     pub dids: Vec<DidItem>,
+
+    /// The teacher's key in external systems, if the school has configured one.
+    #[serde(rename = "externKey", default)]
+    pub extern_id: Option<String>,
+
+    /// The teacher's contact email. Only newer servers send it, and many schools leave it out for privacy.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub email: Option<String>,
+
+    /// Whether the teacher can be contacted through the WebUntis messenger, if the server says so.
+    #[serde(rename = "enableMessenger", default)]
+    pub messenger_enabled: Option<bool>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -272,16 +505,24 @@ pub struct Student {
 
     /// The student's gender.
     pub gender: String,
+
+    /// The student's key in external systems, if the school has configured one.
+    #[serde(rename = "externKey", default)]
+    pub extern_id: Option<String>,
 }
 
 /// A school lesson.
+///
+/// Every lesson takes place on a single day. Activities that span multiple days, like field trips or project weeks,
+/// are sent as one lesson per day, each with its own id, date and times, so a timetable for a week contains every
+/// day of the activity that falls into it.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Lesson {
     /// The lesson's id.
     pub id: usize,
 
-    /// The lesson's date.
+    /// The lesson's date. Lessons never cross midnight, see [`Lesson`] for activities spanning multiple days.
     pub date: Date,
 
     /// The lesson's start time.
@@ -291,7 +532,7 @@ pub struct Lesson {
     pub end_time: Time,
 
     /// The type of lesson.
-    #[serde(rename = "lstype", default)]
+    #[serde(rename = "lstype", alias = "lsType", alias = "lessonType", default)]
     pub lesson_type: LessonType,
 
     /// The lesson's code.
@@ -299,39 +540,212 @@ pub struct Lesson {
     pub code: LessonCode,
 
     /// Unique id for this specific schedule.
+    #[serde(alias = "lsNumber")]
     pub lsnumber: usize,
 
     /// Info Text for this specific lesson.
-    #[serde(default)]
+    #[serde(alias = "lsText", default)]
     pub lstext: String,
 
     /// Possible substitution text.
+    #[serde(alias = "substtext")]
     pub subst_text: Option<String>,
 
+    /// The student group that this lesson is held for, e.g. a course that only part of a class attends.
+    #[serde(rename = "sg", alias = "studentGroup", default)]
+    pub student_group: Option<String>,
+
     /// The classes that are part of this lesson.
-    #[serde(rename = "kl")]
+    #[serde(
+        rename = "kl",
+        alias = "klassen",
+        alias = "classes",
+        default,
+        deserialize_with = "null_as_empty"
+    )]
     pub classes: Vec<IdItem>,
 
     /// The subjects that are taught in this lesson.
-    #[serde(rename = "su")]
+    #[serde(
+        rename = "su",
+        alias = "subjects",
+        default,
+        deserialize_with = "null_as_empty"
+    )]
     pub subjects: Vec<IdItem>,
 
     /// The rooms that this lesson takes place in.
-    #[serde(rename = "ro")]
+    #[serde(
+        rename = "ro",
+        alias = "rooms",
+        default,
+        deserialize_with = "null_as_empty"
+    )]
     pub rooms: Vec<IdItem>,
 
     /// The teachers which are teaching this lesson.
-    #[serde(rename = "te", default)]
+    #[serde(
+        rename = "te",
+        alias = "teachers",
+        default,
+        deserialize_with = "null_as_empty"
+    )]
     pub teachers: Vec<IdItem>,
 
-    #[serde(default)]
+    #[serde(alias = "statFlags", default)]
     pub statflags: String,
 
     /// The lesson's activity type.
-    #[serde(default = "default_activity_type")]
+    #[serde(alias = "activitytype", default = "default_activity_type")]
     pub activity_type: String,
 }
 
+impl Lesson {
+    /// Returns whether both lessons take place on the same date at overlapping times.
+    /// Lessons that merely touch, i.e. one ends exactly when the other starts, don't conflict.
+    pub fn conflicts_with(&self, other: &Lesson) -> bool {
+        self.overlaps(&other.date, &other.start_time, &other.end_time)
+    }
+
+    /// Returns the date and time that the lesson starts at, in the school's timezone.
+    pub fn start_datetime(&self) -> chrono::NaiveDateTime {
+        self.date.and_time(*self.start_time)
+    }
+
+    /// Returns the date and time that the lesson ends at, in the school's timezone.
+    pub fn end_datetime(&self) -> chrono::NaiveDateTime {
+        self.date.and_time(*self.end_time)
+    }
+
+    /// Returns the lesson's start as an RFC 3339 timestamp like `2024-05-01T08:00:00+02:00`, e.g. for a JavaScript
+    /// frontend, with the offset that `timezone` has on the lesson's date.
+    ///
+    /// Untis only sends local times, so `timezone` has to be the school's, e.g. the `chrono-tz` zone named by
+    /// [`School::timezone`]. Returns `None` if the time doesn't exist in it because clocks were moved forward.
+    pub fn start_rfc3339<Tz: chrono::TimeZone>(&self, timezone: &Tz) -> Option<String>
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        rfc3339(timezone, self.start_datetime())
+    }
+
+    /// Like [`start_rfc3339()`](Self::start_rfc3339), but for the lesson's end.
+    pub fn end_rfc3339<Tz: chrono::TimeZone>(&self, timezone: &Tz) -> Option<String>
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        rfc3339(timezone, self.end_datetime())
+    }
+
+    /// Returns whether the names of the lesson's teachers are known.
+    ///
+    /// Accounts with restricted rights, usually students, often may see their own timetable but not who teaches it. The
+    /// server then leaves out the teachers or sends them without names, so UIs can show "teacher hidden" instead of
+    /// an empty field.
+    pub fn has_teacher_info(&self) -> bool {
+        self.teachers.iter().any(|teacher| !teacher.name.is_empty())
+    }
+
+    /// Returns the name of the lesson's first subject, or `None` if it has no subjects.
+    /// Combined lessons list several subjects, in which case the first one in the order sent by the server is used.
+    pub fn primary_subject(&self) -> Option<&str> {
+        self.subjects.first().map(|subject| subject.name.as_str())
+    }
+
+    /// Returns the name of the lesson's first teacher, or `None` if it has no teachers.
+    /// See [`primary_subject()`](Self::primary_subject) for how it is picked.
+    pub fn primary_teacher(&self) -> Option<&str> {
+        self.teachers.first().map(|teacher| teacher.name.as_str())
+    }
+
+    /// Returns the name of the lesson's first room, or `None` if it has no rooms.
+    /// See [`primary_subject()`](Self::primary_subject) for how it is picked.
+    pub fn primary_room(&self) -> Option<&str> {
+        self.rooms.first().map(|room| room.name.as_str())
+    }
+
+    /// Returns the index of the period that the lesson starts in within the [`time_units`](TimegridDay::time_units)
+    /// of its weekday, e.g. 0 for the first period. The period's [`name`](TimeUnit::name) is its label.
+    ///
+    /// Lessons that start up to [`PERIOD_START_TOLERANCE_MINUTES`] before or after a period are assigned to it, as
+    /// some schools shift single lessons by a few minutes. Otherwise the period that the start time falls into is
+    /// used. Returns `None` if the timegrid has no such period, e.g. for lessons outside of the regular hours.
+    pub fn period_index(&self, timegrid: &[TimegridDay]) -> Option<u32> {
+        let weekday = self.date.weekday();
+        let units = &timegrid
+            .iter()
+            .find(|day| day.weekday() == Some(weekday))?
+            .time_units;
+        let tolerance = chrono::Duration::minutes(PERIOD_START_TOLERANCE_MINUTES);
+        let index = units
+            .iter()
+            .position(|unit| (*unit.start_time - *self.start_time).abs() <= tolerance)
+            .or_else(|| {
+                units.iter().position(|unit| {
+                    unit.start_time <= self.start_time && self.start_time < unit.end_time
+                })
+            })?;
+        Some(index as u32)
+    }
+
+    /// Returns the short names of the original and the replacement subject if the lesson's subject was swapped,
+    /// e.g. `("MA", "STU")` for a math lesson that became a study hall.
+    ///
+    /// Only the first substituted subject is reported. Returns `None` if no subject was swapped or the server didn't
+    /// send the original subject's name.
+    pub fn subject_change(&self) -> Option<(&str, &str)> {
+        self.subjects.iter().find_map(|subject| {
+            subject.orgid?;
+            Some((subject.orgname.as_deref()?, subject.name.as_str()))
+        })
+    }
+
+    /// Returns whether the teacher with the given id covers this lesson for another teacher, i.e. whether the lesson
+    /// lists them with the [`orgid`](IdItem::orgid) of the teacher they replace.
+    pub fn is_substituted_by(&self, teacher_id: usize) -> bool {
+        self.teachers.iter().any(|teacher| {
+            teacher.id == teacher_id as isize
+                && teacher.orgid.is_some_and(|orgid| orgid != teacher.id)
+        })
+    }
+
+    /// Returns whether this lesson takes place on `date` and overlaps with the time range from `start` to `end`.
+    pub(crate) fn overlaps(&self, date: &Date, start: &Time, end: &Time) -> bool {
+        self.date == *date && self.start_time < *end && *start < self.end_time
+    }
+}
+
+/// Deserializes an optional string, treating empty strings like absent values.
+fn empty_as_none<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|value| !value.trim().is_empty()))
+}
+
+/// Deserializes a list that restricted accounts may receive as `null`, treating it as empty.
+/// Lists that are left out entirely are handled by `#[serde(default)]`.
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<IdItem>, D::Error> {
+    Ok(Option::<Vec<IdItem>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Formats a local time in the given timezone. Times that exist twice because clocks were moved back are resolved
+/// to the first occurrence.
+fn rfc3339<Tz: chrono::TimeZone>(timezone: &Tz, local: chrono::NaiveDateTime) -> Option<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    timezone
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|datetime| datetime.to_rfc3339())
+}
+
+/// How many minutes a lesson may start before or after a period and still be assigned to it by
+/// [`Lesson::period_index()`].
+pub const PERIOD_START_TOLERANCE_MINUTES: i64 = 5;
+
 /// Represents the status of a lesson (regular, cancelled, etc.)
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Debug, Serialize)]
 pub enum LessonCode {
@@ -341,11 +755,22 @@ pub enum LessonCode {
     Cancelled,
 }
 
+impl LessonCode {
+    fn untis_key(&self) -> Option<&'static str> {
+        match self {
+            LessonCode::Regular => None,
+            LessonCode::Irregular => Some("irregular"),
+            LessonCode::Cancelled => Some("cancelled"),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for LessonCode {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Variant names are accepted as well, so that serialized lessons can be read again.
         Ok(match String::deserialize(deserializer)?.as_str() {
-            "irregular" => LessonCode::Irregular,
-            "cancelled" => LessonCode::Cancelled,
+            "irregular" | "Irregular" => LessonCode::Irregular,
+            "cancelled" | "Cancelled" => LessonCode::Cancelled,
             _ => LessonCode::Regular,
         })
     }
@@ -362,13 +787,26 @@ pub enum LessonType {
     Exam,
 }
 
+impl LessonType {
+    fn untis_key(&self) -> &'static str {
+        match self {
+            LessonType::Lesson => "ls",
+            LessonType::OfficeHour => "oh",
+            LessonType::Standby => "sb",
+            LessonType::BreakSupervision => "bs",
+            LessonType::Exam => "ex",
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for LessonType {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Variant names are accepted as well, so that serialized lessons can be read again.
         Ok(match String::deserialize(deserializer)?.as_str() {
-            "oh" => LessonType::OfficeHour,
-            "sb" => LessonType::Standby,
-            "bs" => LessonType::BreakSupervision,
-            "ex" => LessonType::Exam,
+            "oh" | "OfficeHour" => LessonType::OfficeHour,
+            "sb" | "Standby" => LessonType::Standby,
+            "bs" | "BreakSupervision" => LessonType::BreakSupervision,
+            "ex" | "Exam" => LessonType::Exam,
             _ => LessonType::Lesson,
         })
     }
@@ -380,12 +818,17 @@ pub struct IdItem {
     /// The element's id.
     pub id: isize,
 
-    /// The element's short name.
+    /// The element's short name. Empty if the user isn't allowed to see it.
+    #[serde(default)]
     pub name: String,
 
     /// If this element is a substitute, this is the id of the original element.
-    #[serde(rename = "original_id")]
+    #[serde(rename = "original_id", alias = "orgid")]
     pub orgid: Option<isize>,
+
+    /// If this element is a substitute, this is the short name of the original element, if the server sent it.
+    #[serde(rename = "original_name", alias = "orgname", default)]
+    pub orgname: Option<String>,
 }
 
 /// Represents a school department.
@@ -409,3 +852,521 @@ fn default_id() -> isize {
 fn default_activity_type() -> String {
     String::from("undefined")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lesson;
+
+    #[test]
+    fn session_contains_class_of_students_only() {
+        let student: Session = serde_json::from_value(serde_json::json!({
+            "sessionId": "ABC123",
+            "personType": 5,
+            "personId": 1234,
+            "klasseId": 42,
+        }))
+        .unwrap();
+        assert_eq!(student.class_id, Some(42));
+        assert_eq!(student.person_type, ElementType::Student);
+
+        let teacher: Session = serde_json::from_value(serde_json::json!({
+            "sessionId": "ABC123",
+            "personType": 2,
+            "personId": 12,
+            "klasseId": 0,
+        }))
+        .unwrap();
+        assert_eq!(teacher.class_id, None);
+    }
+
+    #[test]
+    fn own_element_matches_person_type() {
+        let session = |person_type: u8, person_id: usize| -> Session {
+            serde_json::from_value(serde_json::json!({
+                "sessionId": "ABC123",
+                "personType": person_type,
+                "personId": person_id,
+                "klasseId": 42,
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            session(2, 12).own_element(),
+            Some(ElementRef::new(12, ElementType::Teacher))
+        );
+        assert_eq!(
+            session(5, 1234).own_element(),
+            Some(ElementRef::new(1234, ElementType::Student))
+        );
+        assert_eq!(session(2, 0).own_element(), None);
+        assert_eq!(session(1, 42).own_element(), None);
+    }
+
+    #[test]
+    fn lesson_accepts_short_keys() {
+        let lesson: Lesson = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "date": 20240506,
+            "startTime": 800,
+            "endTime": 850,
+            "lstype": "ex",
+            "lsnumber": 100,
+            "kl": [{ "id": 1, "name": "1A" }],
+            "su": [{ "id": 2, "name": "MA" }],
+            "ro": [{ "id": 3, "name": "R1", "orgid": 4 }],
+            "te": [{ "id": 5, "name": "ABC" }],
+        }))
+        .unwrap();
+
+        assert_eq!(lesson.lesson_type, LessonType::Exam);
+        assert_eq!(lesson.classes[0].name, "1A");
+        assert_eq!(lesson.subjects[0].name, "MA");
+        assert_eq!(lesson.rooms[0].orgid, Some(4));
+        assert_eq!(lesson.teachers[0].name, "ABC");
+    }
+
+    #[test]
+    fn lesson_accepts_long_keys() {
+        let lesson: Lesson = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "date": 20240506,
+            "startTime": 800,
+            "endTime": 850,
+            "lessonType": "oh",
+            "lsNumber": 100,
+            "lsText": "Bring calculators",
+            "klassen": [{ "id": 1, "name": "1A" }],
+            "subjects": [{ "id": 2, "name": "MA" }],
+            "rooms": [{ "id": 3, "name": "R1" }],
+            "teachers": [{ "id": 5, "name": "ABC" }],
+            "statFlags": "x",
+        }))
+        .unwrap();
+
+        assert_eq!(lesson.lesson_type, LessonType::OfficeHour);
+        assert_eq!(lesson.lsnumber, 100);
+        assert_eq!(lesson.lstext, "Bring calculators");
+        assert_eq!(lesson.classes[0].name, "1A");
+        assert_eq!(lesson.subjects[0].name, "MA");
+        assert_eq!(lesson.rooms[0].name, "R1");
+        assert_eq!(lesson.teachers[0].name, "ABC");
+        assert_eq!(lesson.statflags, "x");
+    }
+
+    #[test]
+    fn lesson_survives_serialization() {
+        let lesson = lesson(1)
+            .set("lstype", serde_json::json!("ex"))
+            .code("cancelled")
+            .set("substText", serde_json::json!("Room changed"))
+            .set("sg", serde_json::json!("1a_M"))
+            .class(1, "1a")
+            .subject(2, "M")
+            .room(3, "R1")
+            .element(
+                "te",
+                serde_json::json!({ "id": 4, "name": "ABC", "orgid": 5 }),
+            )
+            .build();
+        let json = serde_json::to_string(&lesson).unwrap();
+        assert_eq!(serde_json::from_str::<Lesson>(&json).unwrap(), lesson);
+    }
+
+    #[test]
+    fn substitutes_are_listed_with_the_original_teacher() {
+        let lesson = lesson(1)
+            .element(
+                "te",
+                serde_json::json!({ "id": 4, "name": "ABC", "orgid": 5 }),
+            )
+            .element(
+                "te",
+                serde_json::json!({ "id": 6, "name": "DEF", "orgid": 6 }),
+            )
+            .build();
+        assert!(lesson.is_substituted_by(4));
+        assert!(!lesson.is_substituted_by(5));
+        assert!(!lesson.is_substituted_by(6));
+    }
+
+    #[test]
+    fn teacher_contact_fields_are_optional() {
+        let contactable: Teacher = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "ABC",
+            "foreName": "Anna",
+            "longName": "Bauer",
+            "title": "",
+            "active": true,
+            "dids": [],
+            "email": "anna.bauer@school.example",
+            "enableMessenger": true,
+        }))
+        .unwrap();
+        assert_eq!(
+            contactable.email.as_deref(),
+            Some("anna.bauer@school.example")
+        );
+        assert_eq!(contactable.messenger_enabled, Some(true));
+
+        let restricted: Teacher = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "name": "DEF",
+            "foreName": "",
+            "longName": "",
+            "title": "",
+            "active": true,
+            "dids": [],
+            "email": "",
+        }))
+        .unwrap();
+        assert_eq!(restricted.email, None);
+        assert_eq!(restricted.messenger_enabled, None);
+    }
+
+    #[test]
+    fn extern_id_is_optional() {
+        let teacher: Teacher = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "ABC",
+            "foreName": "Anna",
+            "longName": "Bauer",
+            "title": "",
+            "active": true,
+            "dids": [],
+            "externKey": "T-0001",
+        }))
+        .unwrap();
+        assert_eq!(teacher.extern_id.as_deref(), Some("T-0001"));
+
+        let student: Student = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "key": "123",
+            "name": "BauerMax",
+            "foreName": "Max",
+            "longName": "Bauer",
+            "gender": "male",
+        }))
+        .unwrap();
+        assert_eq!(student.extern_id, None);
+
+        let subject: Subject = serde_json::from_value(serde_json::json!({
+            "id": 3,
+            "name": "M",
+            "longName": "Mathematics",
+            "alternateName": "MAT-01",
+            "active": true,
+            "externKey": "0110",
+        }))
+        .unwrap();
+        assert_eq!(subject.alternate_name, "MAT-01");
+        assert_eq!(subject.extern_id.as_deref(), Some("0110"));
+    }
+
+    #[test]
+    fn lessons_conflict_when_overlapping() {
+        let first = lesson(1).build();
+        assert!(first.conflicts_with(&lesson(2).time(840, 930).build()));
+        assert!(first.conflicts_with(&lesson(2).time(810, 820).build()));
+        assert!(lesson(2).time(810, 820).build().conflicts_with(&first));
+    }
+
+    #[test]
+    fn lessons_dont_conflict_when_touching_or_on_other_dates() {
+        let first = lesson(1).build();
+        assert!(!first.conflicts_with(&lesson(2).time(850, 940).build()));
+        assert!(!first.conflicts_with(&lesson(2).date(20240507).build()));
+    }
+
+    /// Central European Time with the daylight saving time rules of 2024, which start on March 31st.
+    #[derive(Clone, Copy, Debug)]
+    struct Cet2024;
+
+    impl Cet2024 {
+        fn offset_on(date: chrono::NaiveDate) -> chrono::FixedOffset {
+            let summer = (chrono::NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+                ..chrono::NaiveDate::from_ymd_opt(2024, 10, 27).unwrap())
+                .contains(&date);
+            chrono::FixedOffset::east_opt(if summer { 7200 } else { 3600 }).unwrap()
+        }
+    }
+
+    impl chrono::TimeZone for Cet2024 {
+        type Offset = chrono::FixedOffset;
+
+        fn from_offset(_: &chrono::FixedOffset) -> Self {
+            Cet2024
+        }
+
+        fn offset_from_local_date(
+            &self,
+            local: &chrono::NaiveDate,
+        ) -> chrono::LocalResult<chrono::FixedOffset> {
+            chrono::LocalResult::Single(Self::offset_on(*local))
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &chrono::NaiveDateTime,
+        ) -> chrono::LocalResult<chrono::FixedOffset> {
+            chrono::LocalResult::Single(Self::offset_on(local.date()))
+        }
+
+        fn offset_from_utc_date(&self, utc: &chrono::NaiveDate) -> chrono::FixedOffset {
+            Self::offset_on(*utc)
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> chrono::FixedOffset {
+            Self::offset_on(utc.date())
+        }
+    }
+
+    #[test]
+    fn lesson_times_use_the_offset_of_their_date() {
+        let friday = lesson(1).date(20240329).build();
+        assert_eq!(
+            friday.start_rfc3339(&Cet2024).unwrap(),
+            "2024-03-29T08:00:00+01:00"
+        );
+        let tuesday = lesson(1).date(20240402).build();
+        assert_eq!(
+            tuesday.start_rfc3339(&Cet2024).unwrap(),
+            "2024-04-02T08:00:00+02:00"
+        );
+        assert_eq!(
+            tuesday.end_rfc3339(&chrono::Utc).unwrap(),
+            "2024-04-02T08:50:00+00:00"
+        );
+    }
+
+    #[test]
+    fn restricted_lessons_may_lack_elements() {
+        let restricted: Lesson = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "date": 20240506,
+            "startTime": 800,
+            "endTime": 850,
+            "lsnumber": 100,
+            "su": [{ "id": 2, "name": "MA" }],
+            "ro": null,
+        }))
+        .unwrap();
+        assert!(restricted.classes.is_empty());
+        assert!(restricted.rooms.is_empty());
+        assert!(restricted.teachers.is_empty());
+        assert!(!restricted.has_teacher_info());
+
+        let anonymous = lesson(1)
+            .class(1, "1A")
+            .element("te", serde_json::json!({ "id": 5 }))
+            .build();
+        assert_eq!(anonymous.teachers[0].name, "");
+        assert!(!anonymous.has_teacher_info());
+        assert!(lesson(1).teacher(5, "ABC").build().has_teacher_info());
+    }
+
+    #[test]
+    fn swapped_subjects_are_reported() {
+        let swapped = lesson(1)
+            .code("irregular")
+            .class(1, "1A")
+            .element(
+                "su",
+                serde_json::json!({ "id": 7, "name": "STU", "orgid": 2, "orgname": "MA" }),
+            )
+            .room(3, "R1")
+            .teacher(5, "ABC")
+            .build();
+        assert_eq!(swapped.subject_change(), Some(("MA", "STU")));
+        assert_eq!(lesson(1).build().subject_change(), None);
+    }
+
+    #[test]
+    fn lessons_are_matched_to_periods_with_tolerance() {
+        let timegrid: Vec<TimegridDay> = serde_json::from_value(serde_json::json!([{
+            "day": 2,
+            "timeUnits": [
+                { "name": "1", "startTime": 800, "endTime": 845 },
+                { "name": "2", "startTime": 850, "endTime": 935 },
+            ],
+        }]))
+        .unwrap();
+        assert_eq!(timegrid[0].weekday(), Some(chrono::Weekday::Mon));
+
+        assert_eq!(
+            lesson(1).time(800, 845).build().period_index(&timegrid),
+            Some(0)
+        );
+        assert_eq!(
+            lesson(1).time(855, 935).build().period_index(&timegrid),
+            Some(1)
+        );
+        assert_eq!(
+            lesson(1).time(910, 935).build().period_index(&timegrid),
+            Some(1)
+        );
+        assert_eq!(
+            lesson(1).time(1300, 1345).build().period_index(&timegrid),
+            None
+        );
+        assert_eq!(
+            lesson(1)
+                .date(20240507)
+                .time(800, 845)
+                .build()
+                .period_index(&timegrid),
+            None
+        );
+    }
+
+    #[test]
+    fn primary_elements_are_the_first_ones() {
+        let lesson = lesson(1)
+            .subject(1, "M")
+            .subject(2, "PH")
+            .room(3, "R1")
+            .build();
+        assert_eq!(lesson.primary_subject(), Some("M"));
+        assert_eq!(lesson.primary_room(), Some("R1"));
+        assert_eq!(lesson.primary_teacher(), None);
+    }
+
+    #[test]
+    fn status_data_colors_by_type_and_code() {
+        let status: StatusData = serde_json::from_value(serde_json::json!({
+            "lstypes": [
+                { "ls": { "foreColor": "000000", "backColor": "f49f25" } },
+                { "ex": { "foreColor": "000000", "backColor": "ff0000" } },
+            ],
+            "codes": [
+                { "cancelled": { "foreColor": "000000", "backColor": "b1b3b4" } },
+            ],
+        }))
+        .unwrap();
+
+        let exam = status.lesson_type_color(LessonType::Exam).unwrap();
+        assert_eq!(exam.back_color, Color::from_hex("ff0000"));
+        assert!(status.lesson_type_color(LessonType::Standby).is_none());
+
+        let cancelled = status.code_color(LessonCode::Cancelled).unwrap();
+        assert_eq!(
+            cancelled.back_color.map(|color| color.as_rgb()),
+            Some((0xb1, 0xb3, 0xb4))
+        );
+        assert!(status.code_color(LessonCode::Regular).is_none());
+    }
+
+    #[test]
+    fn schoolyear_includes_start_and_end_date() {
+        let year: Schoolyear = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "2023/2024",
+            "startDate": 20230904,
+            "endDate": 20240630,
+        }))
+        .unwrap();
+        let date = |ymd: u32| serde_json::from_value::<Date>(serde_json::json!(ymd)).unwrap();
+
+        assert!(year.is_current(&date(20230904)));
+        assert!(year.is_current(&date(20240630)));
+        assert!(!year.is_current(&date(20230903)));
+        assert!(!year.is_current(&date(20240701)));
+    }
+
+    #[test]
+    fn schoolyear_clamps_ranges_to_its_bounds() {
+        let year: Schoolyear = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "2023/2024",
+            "startDate": 20230904,
+            "endDate": 20240630,
+        }))
+        .unwrap();
+        let date = |ymd: u32| serde_json::from_value::<Date>(serde_json::json!(ymd)).unwrap();
+
+        assert_eq!(
+            year.clamp_range(date(20230801), date(20231001)),
+            Some((date(20230904), date(20231001)))
+        );
+        assert_eq!(
+            year.clamp_range(date(20240601), date(20240731)),
+            Some((date(20240601), date(20240630)))
+        );
+        assert_eq!(year.clamp_range(date(20240701), date(20240731)), None);
+    }
+
+    #[test]
+    fn holiday_overlaps_weeks_partially() {
+        let holiday: Holiday = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "Autumn",
+            "longName": "Autumn holidays",
+            "startDate": 20231026,
+            "endDate": 20231103,
+        }))
+        .unwrap();
+        let date = |ymd: u32| serde_json::from_value::<Date>(serde_json::json!(ymd)).unwrap();
+
+        assert_eq!(
+            holiday.clamp_range(date(20231023), date(20231028)),
+            Some((date(20231026), date(20231028)))
+        );
+        assert_eq!(
+            holiday.clamp_range(date(20231030), date(20231104)),
+            Some((date(20231030), date(20231103)))
+        );
+        assert_eq!(holiday.clamp_range(date(20231106), date(20231111)), None);
+    }
+
+    #[test]
+    fn school_metadata_is_optional() {
+        let school = serde_json::json!({
+            "server": "ajax.webuntis.com",
+            "useMobileServiceUrlAndroid": true,
+            "address": "Street 1, 1234 City",
+            "displayName": "Demo School",
+            "loginName": "demo",
+            "schoolId": 1,
+            "useMobileServiceUrlIos": true,
+            "serverUrl": "https://ajax.webuntis.com/WebUntis/?school=demo",
+            "mobileServiceUrl": null,
+        });
+        let without: School = serde_json::from_value(school.clone()).unwrap();
+        assert_eq!(without.tenant_id, None);
+        assert_eq!(without.timezone, None);
+        assert_eq!(without.city, None);
+        assert_eq!(without.region, None);
+
+        let mut school = school;
+        school["tenantId"] = serde_json::json!("1234500");
+        school["timezone"] = serde_json::json!("Europe/Vienna");
+        school["city"] = serde_json::json!("City");
+        school["region"] = serde_json::json!("Vienna");
+        let with: School = serde_json::from_value(school).unwrap();
+        assert_eq!(with.tenant_id.as_deref(), Some("1234500"));
+        assert_eq!(with.timezone.as_deref(), Some("Europe/Vienna"));
+        assert_eq!(with.city.as_deref(), Some("City"));
+        assert_eq!(with.region.as_deref(), Some("Vienna"));
+    }
+
+    #[test]
+    fn school_candidate_servers_include_server_url_host() {
+        let school = School {
+            server: String::from("ajax.webuntis.com"),
+            server_url: String::from("https://nessa.webuntis.com/WebUntis/?school=demo"),
+            ..School::default()
+        };
+        assert_eq!(
+            school.candidate_servers(),
+            vec!["ajax.webuntis.com", "nessa.webuntis.com"]
+        );
+
+        let school = School {
+            server: String::from("ajax.webuntis.com"),
+            server_url: String::from("https://ajax.webuntis.com/WebUntis/?school=demo"),
+            ..School::default()
+        };
+        assert_eq!(school.candidate_servers(), vec!["ajax.webuntis.com"]);
+    }
+}
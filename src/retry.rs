@@ -0,0 +1,163 @@
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Controls if and how requests are retried when Untis rate-limits the client.
+///
+/// If the server sends a `Retry-After` header, the client waits exactly that long. Otherwise it falls back to an
+/// exponential backoff with random jitter, so that many clients don't retry in lockstep.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct RetryPolicy {
+    /// How many times a request is retried after the first attempt.
+    pub max_retries: u32,
+
+    /// Base delay of the backoff, doubled with every retry.
+    pub base_delay: Duration,
+
+    /// The longest the client is willing to wait before a retry.
+    /// If the server asks for a longer delay, the request fails immediately instead.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Returns how long to wait before retry number `attempt` (starting at 0), or `None` if the request should not be retried.
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        match retry_after {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        // Equal jitter: wait at least half of the exponential delay, plus a random part of the other half.
+        let half = exponential / 2;
+        // The modulo is done in u128, as `half` can be longer than `u64::MAX` nanoseconds for huge maximum delays.
+        let jitter_nanos = (u128::from(random_u64()) % (half.as_nanos() + 1)) as u64;
+        half + Duration::from_nanos(jitter_nanos)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Parses a `Retry-After` header value, which is either a number of seconds or an HTTP date.
+pub(crate) fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parse_retry_after_seconds() {
+        assert_eq!(
+            parse_retry_after("120", Utc::now()),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_http_date() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 30).unwrap();
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_in_the_past_is_zero() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 8, 0, 0).unwrap();
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_rejects_garbage() {
+        assert_eq!(parse_retry_after("soon", Utc::now()), None);
+    }
+
+    #[test]
+    fn delay_honors_retry_after() {
+        let policy = RetryPolicy::default();
+        let delay = Duration::from_secs(7);
+        assert_eq!(policy.delay(0, Some(delay)), Some(delay));
+    }
+
+    #[test]
+    fn delay_gives_up_on_too_long_retry_after() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(3600))), None);
+    }
+
+    #[test]
+    fn delay_gives_up_after_max_retries() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(policy.max_retries, None), None);
+        assert_eq!(RetryPolicy::none().delay(0, None), None);
+    }
+
+    #[test]
+    fn backoff_stays_within_bounds() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..policy.max_retries {
+            let full = policy.base_delay * 2u32.pow(attempt);
+            let delay = policy.delay(attempt, None).unwrap();
+            assert!(delay >= full / 2 && delay <= full);
+        }
+    }
+
+    #[test]
+    fn backoff_handles_huge_max_delay() {
+        let policy = RetryPolicy {
+            max_retries: 100,
+            base_delay: Duration::MAX,
+            max_delay: Duration::MAX,
+        };
+        let delay = policy.delay(99, None).unwrap();
+        assert!(delay >= Duration::MAX / 2);
+    }
+}
@@ -6,8 +6,24 @@ fn get_client() -> jsonrpc::Client {
     jsonrpc::Client::new("https://mobile.webuntis.com/ms/schoolquery2")
 }
 
+/// Queries shorter than this always match too many schools, see [`search()`].
+pub const DEFAULT_MIN_QUERY_LENGTH: usize = 3;
+
 /// Returns all schools matching the query or an empty vec if there are too many results.
+///
+/// The school search endpoint has no offset or limit parameters, so results can't be paged. If a query matches too
+/// many schools, the server sends none of them, and the only way to find the school is a more specific query, e.g.
+/// one that includes the city.
+///
+/// Surrounding whitespace is removed. Queries shorter than [`DEFAULT_MIN_QUERY_LENGTH`] characters fail with
+/// [`Error::QueryTooShort`] without sending a request, use [`search_with_min_length()`] for a different limit.
 pub async fn search(query: &str) -> Result<Vec<School>, Error> {
+    search_with_min_length(query, DEFAULT_MIN_QUERY_LENGTH).await
+}
+
+/// Like [`search()`], but with a custom minimum query length. A length of 0 only rejects empty queries.
+pub async fn search_with_min_length(query: &str, min_length: usize) -> Result<Vec<School>, Error> {
+    let query = sanitize_query(query, min_length)?;
     let result = get_client()
         .request(
             "searchSchool",
@@ -17,6 +33,48 @@ pub async fn search(query: &str) -> Result<Vec<School>, Error> {
     catch_too_many(result)
 }
 
+fn sanitize_query(query: &str, min_length: usize) -> Result<&str, Error> {
+    let query = query.trim();
+    if query.is_empty() || query.chars().count() < min_length {
+        Err(Error::QueryTooShort { min_length })
+    } else {
+        Ok(query)
+    }
+}
+
+/// Like [`search()`], but sorts the results by how closely their [`display_name`](School::display_name) or
+/// [`login_name`](School::login_name) matches the query, best match first.
+///
+/// Matching is case-insensitive. Exact matches rank before prefix matches, then matches at the start of a word,
+/// then matches anywhere in the name. Schools with the same rank keep the server's order.
+pub async fn search_ranked(query: &str) -> Result<Vec<School>, Error> {
+    let mut schools = search(query).await?;
+    let query = query.trim().to_lowercase();
+    schools.sort_by_cached_key(|school| {
+        match_rank(&school.display_name, &query).min(match_rank(&school.login_name, &query))
+    });
+    Ok(schools)
+}
+
+/// Returns how well `name` matches the lowercase `query`, lower is better.
+fn match_rank(name: &str, query: &str) -> u8 {
+    let name = name.to_lowercase();
+    if name == query {
+        0
+    } else if name.starts_with(query) {
+        1
+    } else if name
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(query))
+    {
+        2
+    } else if name.contains(query) {
+        3
+    } else {
+        4
+    }
+}
+
 /// Retrieves a school by its id.
 pub async fn get_by_id(id: &usize) -> Result<School, Error> {
     let result = get_client()
@@ -62,3 +120,28 @@ fn catch_too_many(result: Result<SchoolSearchResult, Error>) -> Result<Vec<Schoo
         Err(err) => Err(err),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_queries_are_rejected() {
+        assert!(matches!(
+            sanitize_query(" a ", DEFAULT_MIN_QUERY_LENGTH),
+            Err(Error::QueryTooShort { min_length: 3 })
+        ));
+        assert!(sanitize_query("  ", 0).is_err());
+        assert_eq!(sanitize_query(" Graz ", 3).unwrap(), "Graz");
+        assert_eq!(sanitize_query("äöü", 3).unwrap(), "äöü");
+    }
+
+    #[test]
+    fn match_rank_prefers_closer_matches() {
+        assert_eq!(match_rank("Gymnasium", "gymnasium"), 0);
+        assert_eq!(match_rank("Gymnasium Nord", "gymnasium"), 1);
+        assert_eq!(match_rank("BG/BRG Gymnasium", "gymnasium"), 2);
+        assert_eq!(match_rank("Realgymnasium", "gymnasium"), 3);
+        assert_eq!(match_rank("Realschule", "gymnasium"), 4);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    client::{
+        allow_no_access, authenticate, fetch_classes, fetch_current_schoolyear, fetch_departments,
+        fetch_holidays, fetch_last_update_time, fetch_rooms, fetch_schoolyears, fetch_status_data,
+        fetch_students, fetch_subjects, fetch_teachers, fetch_timegrid, fetch_timetable,
+        fetch_timetables, split_at_schoolyears, Credentials,
+    },
+    datetime::Date,
+    error::Error,
+    jsonrpc,
+    resources::*,
+    ClientMetrics, Session, TimetableOptions, Timetables,
+};
+use chrono::Weekday;
+use futures_util::lock::Mutex;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// A handle to a logged in [`Client`](crate::Client) that can be cloned and used from many tasks at once,
+/// e.g. to serve multiple users of a web backend from a single session.
+///
+/// Created with [`Client::into_shared()`](crate::Client::into_shared). All clones share the same session and
+/// connection pool, and their requests run concurrently. Only the read-only queries are available, the caches of
+/// the client are not carried over.
+///
+/// ```no_run
+/// # async fn example(client: untis::Client) -> Result<(), untis::Error> {
+/// let shared = client.into_shared();
+/// let handle = shared.clone();
+/// tokio::spawn(async move { handle.own_timetable_current_week().await });
+/// let rooms = shared.rooms().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SharedClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    rpc_client: jsonrpc::Client,
+    session: RwLock<Session>,
+    server: String,
+    credentials: Credentials,
+    timetable_options: TimetableOptions,
+    week_start: Weekday,
+    reauth: Mutex<()>,
+}
+
+impl SharedClient {
+    pub(crate) fn new(
+        rpc_client: jsonrpc::Client,
+        session: Session,
+        server: String,
+        credentials: Credentials,
+        timetable_options: TimetableOptions,
+        week_start: Weekday,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                rpc_client,
+                session: RwLock::new(session),
+                server,
+                credentials,
+                timetable_options,
+                week_start,
+                reauth: Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Returns the active session.
+    pub fn session(&self) -> Session {
+        self.inner
+            .session
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Returns the server that this client is logged in to.
+    pub fn server(&self) -> &str {
+        &self.inner.server
+    }
+
+    /// Returns the statistics of all requests sent by this client and its clones so far.
+    pub fn metrics(&self) -> ClientMetrics {
+        self.inner.rpc_client.metrics()
+    }
+
+    /// Returns when the server last answered a request of this client or one of its clones.
+    pub fn last_activity(&self) -> Instant {
+        self.inner.rpc_client.last_activity()
+    }
+
+    /// See [`Client::is_probably_expired()`](crate::Client::is_probably_expired).
+    pub fn is_probably_expired(&self, idle_timeout: Duration) -> bool {
+        self.last_activity().elapsed() >= idle_timeout
+    }
+
+    /// See [`Client::ensure_valid()`](crate::Client::ensure_valid).
+    /// If multiple tasks call this at the same time, only one of them logs in again.
+    pub async fn ensure_valid(&self, idle_timeout: Duration) -> Result<(), Error> {
+        if !self.is_probably_expired(idle_timeout) {
+            return Ok(());
+        }
+
+        let _guard = self.inner.reauth.lock().await;
+        // Another task may have logged in again while this one was waiting.
+        if self.is_probably_expired(idle_timeout) {
+            let session = authenticate(&self.inner.rpc_client, &self.inner.credentials).await?;
+            *self
+                .inner
+                .session
+                .write()
+                .unwrap_or_else(|err| err.into_inner()) = session;
+        }
+        Ok(())
+    }
+
+    /// Returns the last time that any timetable at this school was updated.
+    pub async fn last_update_time(&self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+        fetch_last_update_time(&self.inner.rpc_client).await
+    }
+
+    /// Returns status data that can be used for displaying a timetable.
+    pub async fn status_data(&self) -> Result<StatusData, Error> {
+        fetch_status_data(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the current schoolyear.
+    pub async fn current_schoolyear(&self) -> Result<Schoolyear, Error> {
+        fetch_current_schoolyear(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves a list of all schoolyears.
+    pub async fn schoolyears(&self) -> Result<Vec<Schoolyear>, Error> {
+        fetch_schoolyears(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the holidays in the current schoolyear.
+    pub async fn holidays(&self) -> Result<Vec<Holiday>, Error> {
+        fetch_holidays(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the school's timegrid, i.e. the periods of every weekday.
+    pub async fn timegrid(&self) -> Result<Vec<TimegridDay>, Error> {
+        fetch_timegrid(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the list of rooms in the user's school.
+    pub async fn rooms(&self) -> Result<Vec<Room>, Error> {
+        fetch_rooms(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the list of classes in the user's school.
+    pub async fn classes(&self) -> Result<Vec<Class>, Error> {
+        fetch_classes(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the list of subjects in the user's school.
+    pub async fn subjects(&self) -> Result<Vec<Subject>, Error> {
+        fetch_subjects(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the list of teachers in the user's school.
+    pub async fn teachers(&self) -> Result<Vec<Teacher>, Error> {
+        fetch_teachers(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the list of students in the user's school.
+    pub async fn students(&self) -> Result<Vec<Student>, Error> {
+        fetch_students(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the list of departments in the user's school.
+    pub async fn departments(&self) -> Result<Vec<Department>, Error> {
+        fetch_departments(&self.inner.rpc_client).await
+    }
+
+    /// Retrieves the users's own timetable for the current week.
+    pub async fn own_timetable_current_week(&self) -> Result<Vec<Lesson>, Error> {
+        self.own_timetable_for_week(&Date::today()).await
+    }
+
+    /// Retrieves the users's own timetable for the week that a given date is in.
+    pub async fn own_timetable_for_week(&self, date: &Date) -> Result<Vec<Lesson>, Error> {
+        self.own_timetable_between(
+            &date.relative_week_begin_on(self.inner.week_start),
+            &date.relative_week_end_on(self.inner.week_start),
+        )
+        .await
+    }
+
+    /// Retrieves the users's own timetable between two dates, both inclusive.
+    /// Like [`Client::own_timetable_between()`](crate::Client::own_timetable_between), the range is split at
+    /// schoolyear boundaries, but the schoolyears are fetched again on every call.
+    /// Fails with [`Error::NotFound`] if the account isn't linked to a teacher or student.
+    pub async fn own_timetable_between(
+        &self,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        let element = self.session().own_element().ok_or(Error::NotFound)?;
+        let schoolyears = allow_no_access(self.schoolyears().await)?;
+        let mut lessons = Vec::new();
+        for (start, end) in split_at_schoolyears(start_date, end_date, &schoolyears) {
+            lessons.extend(
+                self.element_timetable_between(&element, &start, &end)
+                    .await?,
+            );
+        }
+        Ok(lessons)
+    }
+
+    /// Retrieves an element's timetable for the week that a given date is in.
+    pub async fn timetable_for_week(
+        &self,
+        id: &usize,
+        ty: &ElementType,
+        date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        self.timetable_between(
+            id,
+            ty,
+            &date.relative_week_begin_on(self.inner.week_start),
+            &date.relative_week_end_on(self.inner.week_start),
+        )
+        .await
+    }
+
+    /// Retrieves an element's own timetable between two dates, both inclusive.
+    pub async fn timetable_between(
+        &self,
+        id: &usize,
+        ty: &ElementType,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        self.element_timetable_between(&ElementRef::new(*id, ty.clone()), start_date, end_date)
+            .await
+    }
+
+    /// Retrieves the timetable of an element referenced by id or name between two dates, both inclusive.
+    pub async fn element_timetable_between(
+        &self,
+        element: &ElementRef,
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Vec<Lesson>, Error> {
+        fetch_timetable(
+            &self.inner.rpc_client,
+            &self.inner.timetable_options,
+            self.inner.week_start,
+            element,
+            start_date,
+            end_date,
+        )
+        .await
+    }
+
+    /// Retrieves the timetables of multiple elements between two dates, both inclusive.
+    /// Up to four timetables are fetched at the same time.
+    pub async fn timetables_between(
+        &self,
+        elements: &[ElementRef],
+        start_date: &Date,
+        end_date: &Date,
+    ) -> Result<Timetables, Error> {
+        fetch_timetables(
+            &self.inner.rpc_client,
+            &self.inner.timetable_options,
+            self.inner.week_start,
+            elements,
+            start_date,
+            end_date,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_client_can_be_sent_between_tasks() {
+        fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+        assert_shareable::<SharedClient>();
+    }
+}
@@ -0,0 +1,96 @@
+//! Fixtures shared by the tests of all modules.
+
+// Not every feature combination has tests that use every helper.
+#![allow(dead_code)]
+
+use crate::resources::Lesson;
+use serde_json::{json, Value};
+
+/// Starts building a lesson with the given id for a test.
+/// Unless changed, it is a regular lesson on monday, 2024-05-06 from 8:00 to 8:50 without any elements.
+pub(crate) fn lesson(id: usize) -> LessonBuilder {
+    LessonBuilder {
+        fields: json!({
+            "id": id,
+            "date": 20240506,
+            "startTime": 800,
+            "endTime": 850,
+            "lsnumber": 100,
+            "kl": [],
+            "su": [],
+            "ro": [],
+            "te": [],
+        }),
+    }
+}
+
+/// A lesson under construction, kept as the JSON that the server would send, see [`lesson()`].
+pub(crate) struct LessonBuilder {
+    fields: Value,
+}
+
+impl LessonBuilder {
+    /// Moves the lesson to another date, in the server's `YYYYMMDD` format.
+    pub(crate) fn date(self, date: u32) -> Self {
+        self.set("date", json!(date))
+    }
+
+    /// Lets the lesson start at the given time, in the server's `HHMM` format, and take 50 minutes.
+    pub(crate) fn starts_at(self, start_time: u16) -> Self {
+        let end = start_time / 100 * 60 + start_time % 100 + 50;
+        self.time(start_time, end / 60 * 100 + end % 60)
+    }
+
+    /// Sets the start and end time, in the server's `HHMM` format.
+    pub(crate) fn time(self, start_time: u16, end_time: u16) -> Self {
+        self.set("startTime", json!(start_time))
+            .set("endTime", json!(end_time))
+    }
+
+    /// Sets the lesson number, which is shared by all lessons of the same course.
+    pub(crate) fn lsnumber(self, lsnumber: usize) -> Self {
+        self.set("lsnumber", json!(lsnumber))
+    }
+
+    /// Sets the lesson's code, one of `"irregular"` and `"cancelled"`.
+    pub(crate) fn code(self, code: &str) -> Self {
+        self.set("code", json!(code))
+    }
+
+    /// Adds a class with the given id and short name.
+    pub(crate) fn class(self, id: isize, name: &str) -> Self {
+        self.element("kl", json!({ "id": id, "name": name }))
+    }
+
+    /// Adds a subject with the given id and short name.
+    pub(crate) fn subject(self, id: isize, name: &str) -> Self {
+        self.element("su", json!({ "id": id, "name": name }))
+    }
+
+    /// Adds a room with the given id and short name.
+    pub(crate) fn room(self, id: isize, name: &str) -> Self {
+        self.element("ro", json!({ "id": id, "name": name }))
+    }
+
+    /// Adds a teacher with the given id and short name.
+    pub(crate) fn teacher(self, id: isize, name: &str) -> Self {
+        self.element("te", json!({ "id": id, "name": name }))
+    }
+
+    /// Adds an element to the `kl`, `su`, `ro` or `te` list, e.g. a substitute with an `orgid`.
+    pub(crate) fn element(mut self, list: &str, element: Value) -> Self {
+        self.fields[list].as_array_mut().unwrap().push(element);
+        self
+    }
+
+    /// Sets a field of the lesson's JSON, e.g. `code` or `lstext`.
+    pub(crate) fn set(mut self, key: &str, value: Value) -> Self {
+        self.fields[key] = value;
+        self
+    }
+
+    /// Parses the lesson like one received from the server.
+    pub(crate) fn build(self) -> Lesson {
+        serde_json::from_value(self.fields).unwrap()
+    }
+}
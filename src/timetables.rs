@@ -0,0 +1,450 @@
+use crate::{
+    datetime::Date,
+    error::Error,
+    resources::{ElementRef, IdItem, Lesson, LessonCode, LessonType, TimegridDay},
+};
+use chrono::{Datelike, Duration};
+use std::collections::HashMap;
+
+/// The timetables of multiple elements, fetched together by [`Client::timetables_between()`](crate::Client::timetables_between).
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Timetables {
+    /// Every requested element with its lessons, in the order they were requested.
+    pub entries: Vec<(ElementRef, Vec<Lesson>)>,
+}
+
+impl Timetables {
+    /// Returns the lessons of an element, or `None` if it wasn't requested.
+    pub fn get(&self, element: &ElementRef) -> Option<&[Lesson]> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry == element)
+            .map(|(_, lessons)| lessons.as_slice())
+    }
+
+    /// Returns the elements that have at least one lesson, including cancelled ones.
+    pub fn elements_with_lessons(&self) -> Vec<ElementRef> {
+        self.elements_where(|lessons| !lessons.is_empty())
+    }
+
+    /// Returns the elements that don't have any lessons.
+    pub fn empty_elements(&self) -> Vec<ElementRef> {
+        self.elements_where(|lessons| lessons.is_empty())
+    }
+
+    fn elements_where(&self, predicate: impl Fn(&[Lesson]) -> bool) -> Vec<ElementRef> {
+        self.entries
+            .iter()
+            .filter(|(_, lessons)| predicate(lessons))
+            .map(|(element, _)| element.clone())
+            .collect()
+    }
+}
+
+/// A timetable of which some lessons could not be parsed,
+/// as returned by [`Client::timetable_between_tolerant()`](crate::Client::timetable_between_tolerant).
+#[derive(Debug, Default)]
+pub struct PartialTimetable {
+    /// The lessons that were parsed successfully, in the order they were received.
+    pub lessons: Vec<Lesson>,
+
+    /// The raw lessons that could not be parsed, each with the reason.
+    pub failures: Vec<(serde_json::Value, Error)>,
+}
+
+impl PartialTimetable {
+    /// Parses each lesson on its own, so that a single malformed one doesn't fail the whole timetable.
+    pub fn from_values(values: Vec<serde_json::Value>) -> Self {
+        let mut timetable = Self::default();
+        for value in values {
+            match serde_json::from_value(value.clone()) {
+                Ok(lesson) => timetable.lessons.push(lesson),
+                Err(err) => timetable.failures.push((value, err.into())),
+            }
+        }
+        timetable
+    }
+
+    /// Returns whether every lesson was parsed successfully.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Lessons that are held for the same student group.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LessonGroup {
+    /// The student group, or `None` for the lessons that aren't held for a specific group.
+    pub student_group: Option<String>,
+
+    /// The lessons, in their original order.
+    pub lessons: Vec<Lesson>,
+}
+
+/// Groups lessons by their [student group](Lesson::student_group), e.g. to show a clean list per course.
+///
+/// Groups are ordered by the first appearance of their student group. All lessons without a student group end up
+/// in a single group whose `student_group` is `None`, so for a student that isn't in any group there is exactly one.
+pub fn group_by_student_group(lessons: &[Lesson]) -> Vec<LessonGroup> {
+    let mut groups: Vec<LessonGroup> = Vec::new();
+    for lesson in lessons {
+        match groups
+            .iter_mut()
+            .find(|group| group.student_group == lesson.student_group)
+        {
+            Some(group) => group.lessons.push(lesson.clone()),
+            None => groups.push(LessonGroup {
+                student_group: lesson.student_group.clone(),
+                lessons: vec![lesson.clone()],
+            }),
+        }
+    }
+    groups
+}
+
+/// How much a teacher teaches in a range of dates, as returned by
+/// [`Client::teacher_workload()`](crate::Client::teacher_workload).
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Workload {
+    /// Number of lessons taught. A double lesson that Untis sends as a single entry counts once.
+    pub periods: usize,
+
+    /// Total length of all lessons taught, in minutes.
+    pub minutes: i64,
+
+    /// Sorted short names of the distinct subjects taught.
+    pub subjects: Vec<String>,
+
+    /// Sorted short names of the distinct classes taught.
+    pub classes: Vec<String>,
+}
+
+impl Workload {
+    /// Sums up the lessons of a teacher's timetable.
+    ///
+    /// Only regular lessons and exams count. Cancelled lessons, break supervisions, standby duties and office hours
+    /// are skipped.
+    pub fn from_lessons(lessons: &[Lesson]) -> Self {
+        let mut workload = Self::default();
+        let taught = lessons.iter().filter(|lesson| {
+            lesson.code != LessonCode::Cancelled
+                && matches!(lesson.lesson_type, LessonType::Lesson | LessonType::Exam)
+        });
+        for lesson in taught {
+            workload.periods += 1;
+            workload.minutes += (*lesson.end_time - *lesson.start_time).num_minutes();
+            let names = |items: &[IdItem]| {
+                items
+                    .iter()
+                    .map(|item| item.name.clone())
+                    .collect::<Vec<_>>()
+            };
+            workload.subjects.extend(names(&lesson.subjects));
+            workload.classes.extend(names(&lesson.classes));
+        }
+        for names in [&mut workload.subjects, &mut workload.classes] {
+            names.sort();
+            names.dedup();
+        }
+        workload
+    }
+}
+
+/// How often a subject is taught, as returned by [`summarize_week()`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SubjectSummary {
+    /// The subject's short name.
+    pub subject: String,
+
+    /// Number of lessons of the subject.
+    pub periods: usize,
+
+    /// The distinct days that the subject is taught on, in chronological order.
+    pub days: Vec<Date>,
+}
+
+/// Sums up a timetable per subject, e.g. to answer "how much math do I have this week".
+///
+/// Cancelled lessons are skipped. Lessons with several subjects count for each of them, lessons without a subject,
+/// like break supervisions, for none. Subjects are ordered by name.
+pub fn summarize_week(lessons: &[Lesson]) -> Vec<SubjectSummary> {
+    let mut summaries: Vec<SubjectSummary> = Vec::new();
+    let held = lessons
+        .iter()
+        .filter(|lesson| lesson.code != LessonCode::Cancelled);
+    for lesson in held {
+        for subject in &lesson.subjects {
+            let index = match summaries
+                .iter()
+                .position(|summary| summary.subject == subject.name)
+            {
+                Some(index) => index,
+                None => {
+                    summaries.push(SubjectSummary {
+                        subject: subject.name.clone(),
+                        periods: 0,
+                        days: Vec::new(),
+                    });
+                    summaries.len() - 1
+                }
+            };
+            let summary = &mut summaries[index];
+            summary.periods += 1;
+            if !summary.days.contains(&lesson.date) {
+                summary.days.push(lesson.date);
+            }
+        }
+    }
+    for summary in &mut summaries {
+        summary.days.sort();
+    }
+    summaries.sort_by(|a, b| a.subject.cmp(&b.subject));
+    summaries
+}
+
+/// Sums up the scheduled time of every teacher by their id, e.g. for workload reports.
+///
+/// Cancelled lessons are skipped. A lesson with several teachers is credited in full to each of them, since every
+/// one of them is in the room for its whole length. Substitutes are credited instead of the teachers they replace.
+///
+/// With an empty `timegrid`, the lessons' own lengths are summed. Otherwise every lesson counts as the total length
+/// of the timegrid periods that it overlaps, so that a lesson shortened or shifted by a few minutes still counts as
+/// the full periods. Lessons outside of all periods count with their own length.
+pub fn hours_by_teacher(lessons: &[Lesson], timegrid: &[TimegridDay]) -> HashMap<usize, Duration> {
+    hours_by(lessons, timegrid, |lesson| &lesson.teachers)
+}
+
+/// Like [`hours_by_teacher()`], but sums up the scheduled time of every subject by its id.
+pub fn hours_by_subject(lessons: &[Lesson], timegrid: &[TimegridDay]) -> HashMap<usize, Duration> {
+    hours_by(lessons, timegrid, |lesson| &lesson.subjects)
+}
+
+fn hours_by(
+    lessons: &[Lesson],
+    timegrid: &[TimegridDay],
+    elements: impl Fn(&Lesson) -> &Vec<IdItem>,
+) -> HashMap<usize, Duration> {
+    let mut hours = HashMap::new();
+    let held = lessons
+        .iter()
+        .filter(|lesson| lesson.code != LessonCode::Cancelled);
+    for lesson in held {
+        let duration = scheduled_duration(lesson, timegrid);
+        for element in elements(lesson) {
+            if let Ok(id) = usize::try_from(element.id) {
+                *hours.entry(id).or_insert_with(Duration::zero) += duration;
+            }
+        }
+    }
+    hours
+}
+
+/// Returns the total length of the periods that a lesson overlaps, or its own length if there are none.
+fn scheduled_duration(lesson: &Lesson, timegrid: &[TimegridDay]) -> Duration {
+    let own = *lesson.end_time - *lesson.start_time;
+    let Some(day) = timegrid
+        .iter()
+        .find(|day| day.weekday() == Some(lesson.date.weekday()))
+    else {
+        return own;
+    };
+    let periods = day
+        .time_units
+        .iter()
+        .filter(|unit| unit.start_time < lesson.end_time && lesson.start_time < unit.end_time)
+        .map(|unit| *unit.end_time - *unit.start_time)
+        .fold(Duration::zero(), |total, length| total + length);
+    if periods.is_zero() {
+        own
+    } else {
+        periods
+    }
+}
+
+/// Sorts lessons chronologically by date, then start time.
+/// The sort is stable, so lessons in the same slot keep the order that the server returned them in.
+pub fn sort_lessons(lessons: &mut [Lesson]) {
+    lessons.sort_by_key(|lesson| (lesson.date, lesson.start_time));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lesson;
+    use crate::ElementType;
+    use chrono::Datelike;
+
+    #[test]
+    fn week_summary_counts_held_lessons_per_subject() {
+        let with_subject = |id, date, subject: &str| lesson(id).date(date).subject(1, subject);
+
+        let summaries = summarize_week(&[
+            with_subject(1, 20240508, "MA").build(),
+            with_subject(2, 20240506, "MA").build(),
+            with_subject(3, 20240506, "E").build(),
+            with_subject(5, 20240508, "MA").build(),
+            with_subject(4, 20240509, "MA").code("cancelled").build(),
+            lesson(1).build(),
+        ]);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].subject, "E");
+        assert_eq!(summaries[1].subject, "MA");
+        assert_eq!(summaries[1].periods, 3);
+        let days = summaries[1]
+            .days
+            .iter()
+            .map(|date| date.day())
+            .collect::<Vec<_>>();
+        assert_eq!(days, vec![6, 8]);
+    }
+
+    #[test]
+    fn hours_are_credited_to_every_teacher() {
+        let team_taught = lesson(1).teacher(1, "ABC").teacher(2, "DEF").build();
+        let shortened = lesson(2).time(905, 935).teacher(1, "ABC").build();
+        let cancelled = lesson(3)
+            .starts_at(1000)
+            .code("cancelled")
+            .teacher(1, "ABC")
+            .teacher(2, "DEF")
+            .build();
+        let lessons = [team_taught, shortened, cancelled];
+
+        let hours = hours_by_teacher(&lessons, &[]);
+        assert_eq!(hours[&1], Duration::minutes(80));
+        assert_eq!(hours[&2], Duration::minutes(50));
+
+        let timegrid: Vec<TimegridDay> = serde_json::from_value(serde_json::json!([{
+            "day": 2,
+            "timeUnits": [
+                { "name": "1", "startTime": 800, "endTime": 850 },
+                { "name": "2", "startTime": 900, "endTime": 950 },
+            ],
+        }]))
+        .unwrap();
+        let hours = hours_by_teacher(&lessons, &timegrid);
+        assert_eq!(hours[&1], Duration::minutes(100));
+        assert_eq!(hours_by_subject(&lessons, &timegrid), HashMap::new());
+    }
+
+    #[test]
+    fn workload_counts_taught_lessons_only() {
+        let taught = |id, start_time, subject: &str, class: &str| {
+            lesson(id)
+                .starts_at(start_time)
+                .subject(1, subject)
+                .class(2, class)
+                .build()
+        };
+        let mut cancelled = taught(3, 1000, "PH", "2a");
+        cancelled.code = LessonCode::Cancelled;
+        let mut supervision = taught(4, 1100, "", "");
+        supervision.lesson_type = LessonType::BreakSupervision;
+
+        let workload = Workload::from_lessons(&[
+            taught(1, 800, "MA", "1a"),
+            taught(2, 900, "MA", "1b"),
+            cancelled,
+            supervision,
+        ]);
+        assert_eq!(workload.periods, 2);
+        assert_eq!(workload.minutes, 100);
+        assert_eq!(workload.subjects, vec!["MA"]);
+        assert_eq!(workload.classes, vec!["1a", "1b"]);
+    }
+
+    #[test]
+    fn multiday_activities_appear_on_every_day() {
+        // A field trip from monday to wednesday, as sent by Untis: one entry per day.
+        let trip: Vec<Lesson> = (0..3u32)
+            .map(|day| {
+                lesson(10 + day as usize)
+                    .date(20240506 + day)
+                    .time(800, 1600)
+                    .lsnumber(500)
+                    .set("lstext", serde_json::json!("Field trip"))
+                    .class(1, "1a")
+                    .build()
+            })
+            .collect();
+        let mut lessons = vec![lesson(1).date(20240507).build()];
+        lessons.extend(trip.into_iter().rev());
+        sort_lessons(&mut lessons);
+
+        let trip_days: Vec<u32> = lessons
+            .iter()
+            .filter(|lesson| lesson.lsnumber == 500)
+            .map(|lesson| lesson.date.day())
+            .collect();
+        assert_eq!(trip_days, vec![6, 7, 8]);
+        assert!(lessons
+            .iter()
+            .all(|lesson| lesson.start_datetime() < lesson.end_datetime()));
+    }
+
+    #[test]
+    fn partial_timetable_keeps_parsable_lessons() {
+        let valid = serde_json::to_value(lesson(1).build()).unwrap();
+        let mut malformed = valid.clone();
+        malformed["startTime"] = serde_json::json!("eight");
+
+        let timetable = PartialTimetable::from_values(vec![valid, malformed.clone()]);
+        assert!(!timetable.is_complete());
+        assert_eq!(timetable.lessons.len(), 1);
+        assert_eq!(timetable.failures.len(), 1);
+        assert_eq!(timetable.failures[0].0, malformed);
+    }
+
+    #[test]
+    fn elements_are_split_by_lessons() {
+        let used = ElementRef::new(1, ElementType::Room);
+        let unused = ElementRef::new(2, ElementType::Room);
+        let timetables = Timetables {
+            entries: vec![
+                (used.clone(), vec![lesson(1).build()]),
+                (unused.clone(), vec![]),
+            ],
+        };
+
+        assert_eq!(timetables.elements_with_lessons(), vec![used.clone()]);
+        assert_eq!(timetables.empty_elements(), vec![unused]);
+        assert_eq!(timetables.get(&used).map(<[Lesson]>::len), Some(1));
+    }
+
+    #[test]
+    fn sort_lessons_is_chronological_and_stable() {
+        let mut lessons = vec![
+            lesson(1).date(20240507).build(),
+            lesson(2).starts_at(900).build(),
+            lesson(3).build(),
+            lesson(4).starts_at(900).build(),
+        ];
+        sort_lessons(&mut lessons);
+
+        let ids: Vec<usize> = lessons.iter().map(|lesson| lesson.id).collect();
+        assert_eq!(ids, vec![3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn group_by_student_group_collects_ungrouped_lessons() {
+        let mut first = lesson(1).build();
+        first.student_group = Some(String::from("MA_1A_G1"));
+        let second = lesson(2).starts_at(900).build();
+        let mut third = lesson(3).starts_at(1000).build();
+        third.student_group = Some(String::from("MA_1A_G1"));
+        let fourth = lesson(4).starts_at(1100).build();
+
+        let groups = group_by_student_group(&[first, second, third, fourth]);
+        let groups: Vec<(Option<&str>, Vec<usize>)> = groups
+            .iter()
+            .map(|group| {
+                let ids = group.lessons.iter().map(|lesson| lesson.id).collect();
+                (group.student_group.as_deref(), ids)
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![(Some("MA_1A_G1"), vec![1, 3]), (None, vec![2, 4])]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Conversion of lessons into the JSON shape that the official WebUntis web app consumes.
+
+use crate::{ElementType, IdItem, Lesson, LessonCode, LessonType};
+use serde_json::{json, Value};
+
+impl Lesson {
+    /// Converts this lesson into an entry of the `elementPeriods` of the web app's weekly timetable data.
+    ///
+    /// Like in the web app, elements are only referenced by type and id, and the original element of a substitution
+    /// is given as `orgId`, which is 0 if there is none.
+    pub fn to_webuntis_json(&self) -> Value {
+        let elements: Vec<Value> = [
+            (ElementType::Class, &self.classes),
+            (ElementType::Teacher, &self.teachers),
+            (ElementType::Subject, &self.subjects),
+            (ElementType::Room, &self.rooms),
+        ]
+        .into_iter()
+        .flat_map(|(ty, items)| items.iter().map(move |item| element_json(&ty, item)))
+        .collect();
+
+        let cell_state = match self.code {
+            LessonCode::Regular => "STANDARD",
+            LessonCode::Irregular => "SUBSTITUTION",
+            LessonCode::Cancelled => "CANCEL",
+        };
+
+        json!({
+            "id": self.id,
+            "lessonNumber": self.lsnumber,
+            "lessonText": self.lstext,
+            "substText": self.subst_text.as_deref().unwrap_or_default(),
+            "date": self.date,
+            "startTime": self.start_time,
+            "endTime": self.end_time,
+            "elements": elements,
+            "studentGroup": self.student_group.as_deref().unwrap_or_default(),
+            "cellState": cell_state,
+            "is": {
+                "standard": self.code == LessonCode::Regular,
+                "substitution": self.code == LessonCode::Irregular,
+                "cancelled": self.code == LessonCode::Cancelled,
+                "exam": self.lesson_type == LessonType::Exam,
+            },
+        })
+    }
+}
+
+/// Converts lessons into the web app's `elementPeriods` list, see [`Lesson::to_webuntis_json()`].
+pub fn timetable_to_webuntis_json(lessons: &[Lesson]) -> Value {
+    Value::Array(lessons.iter().map(Lesson::to_webuntis_json).collect())
+}
+
+fn element_json(ty: &ElementType, item: &IdItem) -> Value {
+    json!({
+        "type": ty.as_u8(),
+        "id": item.id,
+        "orgId": item.orgid.unwrap_or(0),
+        "missing": false,
+        "state": if item.orgid.is_some() { "SUBSTITUTED" } else { "REGULAR" },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lesson;
+
+    #[test]
+    fn lesson_matches_web_app_period() {
+        let lesson = lesson(1_234_567)
+            .code("irregular")
+            .lsnumber(45_600)
+            .set("sg", json!("MA_1A"))
+            .set("substText", json!("Room change"))
+            .class(1, "1A")
+            .element("te", json!({ "id": 2, "name": "ABC", "orgid": 3 }))
+            .subject(4, "MA")
+            .room(5, "R1")
+            .build();
+
+        let expected = json!({
+            "id": 1_234_567,
+            "lessonNumber": 45_600,
+            "lessonText": "",
+            "substText": "Room change",
+            "date": 20240506,
+            "startTime": 800,
+            "endTime": 850,
+            "elements": [
+                { "type": 1, "id": 1, "orgId": 0, "missing": false, "state": "REGULAR" },
+                { "type": 2, "id": 2, "orgId": 3, "missing": false, "state": "SUBSTITUTED" },
+                { "type": 3, "id": 4, "orgId": 0, "missing": false, "state": "REGULAR" },
+                { "type": 4, "id": 5, "orgId": 0, "missing": false, "state": "REGULAR" },
+            ],
+            "studentGroup": "MA_1A",
+            "cellState": "SUBSTITUTION",
+            "is": {
+                "standard": false,
+                "substitution": true,
+                "cancelled": false,
+                "exam": false,
+            },
+        });
+        assert_eq!(lesson.to_webuntis_json(), expected);
+        assert_eq!(timetable_to_webuntis_json(&[lesson]), json!([expected]));
+    }
+}